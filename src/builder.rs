@@ -0,0 +1,86 @@
+use alloc::vec::Vec;
+use pi_null::Null;
+
+use crate::{StorageMut, Tree};
+
+/// 以声明的方式描述一棵子树，commit时按正确的顺序插入到树上
+/// 节点的key由外部提供（节点数据由外部维护）
+///
+/// ```ignore
+/// TreeBuilder::node(root)
+///     .child(a)
+///     .child_with(b, |b| b.child(c))
+///     .commit(&mut tree);
+/// ```
+pub struct TreeBuilder<K> {
+	id: K,
+	children: Vec<TreeBuilder<K>>,
+}
+
+impl<K: Null + Eq + Clone + Copy> TreeBuilder<K> {
+	/// 以id为根创建子树
+	pub fn node(id: K) -> Self {
+		TreeBuilder {
+			id,
+			children: Vec::new(),
+		}
+	}
+
+	/// 添加一个叶子节点
+	pub fn child(mut self, id: K) -> Self {
+		self.children.push(TreeBuilder::node(id));
+		self
+	}
+
+	/// 添加一个子节点，并通过f描述该子节点下的子树
+	pub fn child_with<F: FnOnce(TreeBuilder<K>) -> TreeBuilder<K>>(mut self, id: K, f: F) -> Self {
+		self.children.push(f(TreeBuilder::node(id)));
+		self
+	}
+
+	/// 将子树作为根节点插入到树上
+	pub fn commit<S: StorageMut<K>>(self, tree: &mut Tree<K, S>) {
+		self.commit_to(tree, K::null())
+	}
+
+	/// 将子树插入到parent的子节点列表末尾，parent为null时作为根节点插入
+	pub fn commit_to<S: StorageMut<K>>(self, tree: &mut Tree<K, S>, parent: K) {
+		let id = self.id;
+		// 先在未挂到树上的根下构建整棵子树，最后再挂到树上，layer只需设置一次
+		self.build(tree);
+		tree.insert_child(id, parent, usize::MAX);
+	}
+
+	fn build<S: StorageMut<K>>(self, tree: &mut Tree<K, S>) {
+		for child in self.children {
+			let id = child.id;
+			child.build(tree);
+			tree.insert_child(id, self.id, usize::MAX);
+		}
+	}
+}
+
+#[test]
+fn test() {
+    use crate::{Storage, VecTree};
+    let mut tree: Tree<usize, VecTree> = Tree::new(VecTree::default());
+
+    TreeBuilder::node(0)
+        .child(1)
+        .child_with(2, |b| b.child(3).child_with(4, |b| b.child(5)))
+        .child(6)
+        .commit(&mut tree);
+
+    assert_eq!(tree.iter(tree.get_down(0).unwrap().head()).collect::<Vec<_>>(), vec![1, 2, 6]);
+    assert_eq!(tree.iter(tree.get_down(2).unwrap().head()).collect::<Vec<_>>(), vec![3, 4]);
+    assert_eq!(tree.descendant_count(0), 6);
+    assert_eq!(tree.depth(5), Some(4));
+    assert_eq!(tree.root_of(5), Some(0));
+    assert_eq!(tree.validate_subtree(0), Ok(()));
+
+    TreeBuilder::node(7).child(8).commit_to(&mut tree, 4);
+    assert_eq!(tree.iter(tree.get_down(4).unwrap().head()).collect::<Vec<_>>(), vec![5, 7]);
+    assert_eq!(tree.depth(8), Some(5));
+    assert_eq!(tree.descendant_count(0), 8);
+    assert_eq!(tree.validate_subtree(0), Ok(()));
+}
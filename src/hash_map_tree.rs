@@ -0,0 +1,151 @@
+use pi_null::Null;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::{Up, Down, Storage, StorageMut, Layer};
+use crate::roots::{Roots, RootLink, RootMap};
+
+/// 以HashMap存储节点信息的树，适用于任意可hash的稀疏key（如u64）
+pub struct HashMapTree<K> {
+	up: HashMap<K, Up<K>>,
+	down: HashMap<K, Down<K>>,
+	layer: HashMap<K, Layer<K>>,
+	roots: Roots<K, HashMap<K, RootLink<K>>>,
+}
+
+impl<K: Null> Default for HashMapTree<K> {
+	fn default() -> Self {
+		HashMapTree {
+			up: HashMap::default(),
+			down: HashMap::default(),
+			layer: HashMap::default(),
+			roots: Roots::default(),
+		}
+	}
+}
+
+impl<K: Eq + Hash> RootMap<K> for HashMap<K, RootLink<K>> {
+	fn get(&self, k: K) -> Option<&RootLink<K>> {
+		HashMap::get(self, &k)
+	}
+
+	fn get_mut(&mut self, k: K) -> Option<&mut RootLink<K>> {
+		HashMap::get_mut(self, &k)
+	}
+
+	fn insert(&mut self, k: K, link: RootLink<K>) {
+		HashMap::insert(self, k, link);
+	}
+
+	fn remove(&mut self, k: K) -> Option<RootLink<K>> {
+		HashMap::remove(self, &k)
+	}
+
+	fn clear(&mut self) {
+		HashMap::clear(self)
+	}
+}
+
+impl<K: Null + Eq + Hash + Copy> Storage<K> for HashMapTree<K> {
+    fn get_up(&self, k: K) -> Option<&Up<K>> {
+        self.up.get(&k)
+    }
+
+    fn up(&self, k: K) -> &Up<K> {
+        self.up.get(&k).unwrap()
+    }
+
+    fn get_layer(&self, k: K) -> Option<&Layer<K>> {
+        self.layer.get(&k)
+    }
+
+    fn layer(&self, k: K) -> &Layer<K> {
+        self.layer.get(&k).unwrap()
+    }
+
+    fn get_down(&self, k: K) -> Option<&Down<K>> {
+        self.down.get(&k)
+    }
+
+    fn first_root(&self) -> K {
+        self.roots.first()
+    }
+
+    fn next_root(&self, k: K) -> K {
+        self.roots.next(k)
+    }
+
+    fn down(&self, k: K) -> &Down<K> {
+        self.down.get(&k).unwrap()
+    }
+}
+
+impl<K: Null + Eq + Hash + Copy> StorageMut<K> for HashMapTree<K> {
+    fn get_up_mut(&mut self, k: K) -> Option<&mut Up<K>> {
+        self.up.get_mut(&k)
+    }
+
+    fn up_mut(&mut self, k: K) -> &mut Up<K> {
+        self.up.get_mut(&k).unwrap()
+    }
+
+    fn get_down_mut(&mut self, k: K) -> Option<&mut Down<K>> {
+        self.down.get_mut(&k)
+    }
+
+    fn down_mut(&mut self, k: K) -> &mut Down<K> {
+        self.down.get_mut(&k).unwrap()
+    }
+
+    fn set_up(&mut self, k: K, parent: Up<K>) {
+        self.up.insert(k, parent);
+    }
+
+    fn remove_up(&mut self, k: K) {
+        self.up.remove(&k);
+    }
+
+    fn set_layer(&mut self, k: K, layer: Layer<K>) {
+        self.layer.insert(k, layer);
+    }
+
+    fn remove_layer(&mut self, k: K) {
+		self.layer.remove(&k);
+    }
+
+    fn set_down(&mut self, k: K, children: Down<K>) {
+        self.down.insert(k, children);
+    }
+
+    fn remove_down(&mut self, k: K) {
+        self.down.remove(&k);
+    }
+
+    fn set_root(&mut self, k: K) {
+        self.roots.insert(k);
+    }
+
+    fn remove_root(&mut self, k: K) {
+        self.roots.remove(k);
+    }
+
+    fn clear(&mut self) {
+        self.up.clear();
+        self.down.clear();
+        self.layer.clear();
+        self.roots.clear();
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.up.shrink_to_fit();
+        self.down.shrink_to_fit();
+        self.layer.shrink_to_fit();
+        self.roots.map.shrink_to_fit();
+    }
+}
+
+#[test]
+fn test() {
+    let keys = [0x9e37_79b9_7f4a_7c15, 0x1000_0001, 7, 0xdead_beef_0000, 42, 0x0123_4567_89ab_cdef, 0x10, 0x20, 0x30];
+    crate::tests::check_storage(crate::Tree::new(HashMapTree::<u64>::default()), keys);
+}
@@ -1,25 +1,113 @@
+// 关闭默认的std特性时为no_std，只依赖alloc（SlotMapTree、HashMapTree、compute_intervals、to_dot和日志输出需要std）
+#![cfg_attr(all(not(test), not(feature = "std")), no_std)]
+
+extern crate alloc;
+
+// 日志输出，std下使用pi_print_any输出任意类型的值，no_std下不输出日志，format只返回消息模板
+#[cfg(feature = "std")]
+macro_rules! out_any {
+	// 只有消息、没有参数时，pi_print_any::out_any!不能匹配，直接调用
+	($call:path, $message:expr) => {
+		$call!($message)
+	};
+	($($t:tt)*) => {
+		pi_print_any::out_any!($($t)*)
+	};
+}
+#[cfg(not(feature = "std"))]
+macro_rules! out_any {
+	(format, $message:expr) => {
+		$message
+	};
+	($call:path, $message:expr) => {{}};
+	(format, $message:expr, $($value:expr),*) => {{
+		$(let _ = &$value;)*
+		$message
+	}};
+	($call:path, $message:expr, $($value:expr),*) => {{
+		$(let _ = &$value;)*
+	}};
+}
+
 /// 树
 /// 以链表结构维护一个树的子节点
 /// 本模块只关心树中节点的插入、删除等逻辑部分，具体描述树状结构的节点数据由外部维护
+#[cfg(feature = "std")]
 pub mod slot_map_tree;
+pub mod vec_tree;
+#[cfg(feature = "std")]
+pub mod hash_map_tree;
+pub mod builder;
+mod roots;
 
 use core::panic;
-use std::fmt::Debug;
-use std::default::Default;
-use std::ops::Deref;
-use pi_print_any::out_any;
+use core::fmt::Debug;
+use core::default::Default;
+use core::ops::{Deref, DerefMut};
+use core::cmp::Ordering;
+use core::mem;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+use alloc::string::String;
 
 use serde::{Serialize, Deserialize};
+#[cfg(feature = "std")]
 pub use slot_map_tree::{SlotMapTree, TreeKey};
+pub use vec_tree::VecTree;
+#[cfg(feature = "std")]
+pub use hash_map_tree::HashMapTree;
+pub use builder::TreeBuilder;
 
 
 use pi_null::Null;
+#[cfg(feature = "std")]
+use pi_slotmap::{Key, SecondaryMap};
 
 pub enum InsertType {
     Back,
     Front,
 }
 
+/// Tree::insert的插入位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target<K> {
+	/// 作为根节点插入
+	Root,
+	/// 插入到parent的子节点列表中，order的含义与insert_child相同
+	ChildOf { parent: K, order: usize },
+	/// 插入到指定节点之前
+	Before(K),
+	/// 插入到指定节点之后
+	After(K),
+}
+
+/// 树结构变化的通知事件，parent为null表示根节点
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructureEvent<K> {
+	/// 节点（连同其子树）挂到了树上
+	Attached { node: K, parent: K },
+	/// 节点（连同其子树）从树上移除
+	Detached { node: K, parent: K },
+	/// 节点（连同其子树）从old_parent移动到new_parent下
+	Reparented { node: K, old_parent: K, new_parent: K },
+	/// parent的子节点顺序发生了变化
+	Reordered { parent: K },
+}
+
+/// 结构变化的回调，Tree默认使用的回调类型
+/// 要求Send是为了让默认的Tree保持Send（Tree常作为资源在线程间移动），回调不需要Sync（只在&mut Tree的操作中调用）
+/// 不需要在线程间移动Tree时，可以指定Tree的回调类型参数L为Box<dyn FnMut(StructureEvent<K>)>，此时回调不要求Send，Tree也不再是Send
+pub type StructureListener<K> = Box<dyn FnMut(StructureEvent<K>) + Send>;
+
+// 回调只在&mut Tree的操作中访问，&Tree无法访问回调，因此在线程间共享&Tree是安全的，Tree是否Sync不取决于回调
+struct ListenerCell<L>(L);
+
+unsafe impl<L> Sync for ListenerCell<L> {}
+
 pub trait Storage<K: Null> {
 	fn get_up(&self, k: K) -> Option<&Up<K>>;
 	fn up(&self, k: K) -> &Up<K>;
@@ -29,6 +117,17 @@ pub trait Storage<K: Null> {
 
 	fn get_down(&self, k: K) -> Option<&Down<K>>;
 	fn down(&self, k: K) -> &Down<K>;
+
+	/// 第一个根节点，根节点按成为根节点的先后顺序链接，没有根节点时为null
+	/// 默认返回null，不记录根节点的存储可以不实现
+	fn first_root(&self) -> K {
+		K::null()
+	}
+
+	/// 根节点k的下一个根节点，k是最后一个根节点时为null
+	fn next_root(&self, _k: K) -> K {
+		K::null()
+	}
 }
 
 pub trait StorageMut<K: Null>: Storage<K> {
@@ -45,8 +144,135 @@ pub trait StorageMut<K: Null>: Storage<K> {
 	fn down_mut(&mut self, k: K) -> &mut Down<K>;
 	fn remove_down(&mut self, k: K);
 
+	/// 记录根节点，追加到根节点列表的最后，重复设置同一个根节点时只记录一次（保持原来的位置）
 	fn set_root(&mut self, k: K);
+	/// 从根节点列表中删除，其余根节点的先后顺序不变
 	fn remove_root(&mut self, k: K);
+
+	/// 清空所有节点的up、down、layer
+	/// 默认实现从根节点列表出发逐个删除，已断开（不能从根节点到达）的节点无法被清除，存储应尽量自行实现
+	fn clear(&mut self) where K: Copy {
+		let mut stack: Vec<K> = RootsIterator::new(&*self).collect();
+		for root in stack.iter() {
+			self.remove_root(*root);
+		}
+		while let Some(k) = stack.pop() {
+			let mut child = self.get_down(k).map_or(K::null(), |down|{down.head});
+			while !child.is_null() {
+				stack.push(child);
+				child = self.get_up(child).map_or(K::null(), |up|{up.next});
+			}
+			self.remove_up(k);
+			self.remove_down(k);
+			self.remove_layer(k);
+		}
+	}
+
+	/// 释放已删除节点占用的多余容量，默认什么也不做
+	fn shrink_to_fit(&mut self) {}
+}
+
+// Storage和StorageMut是对象安全的，为Box实现后可以使用Tree<K, Box<dyn StorageMut<K>>>在运行时切换存储
+impl<K: Null, S: Storage<K> + ?Sized> Storage<K> for Box<S> {
+	#[inline]
+	fn get_up(&self, k: K) -> Option<&Up<K>> {
+		(**self).get_up(k)
+	}
+	#[inline]
+	fn up(&self, k: K) -> &Up<K> {
+		(**self).up(k)
+	}
+
+	#[inline]
+	fn get_layer(&self, k: K) -> Option<&Layer<K>> {
+		(**self).get_layer(k)
+	}
+	#[inline]
+	fn layer(&self, k: K) -> &Layer<K> {
+		(**self).layer(k)
+	}
+
+	#[inline]
+	fn get_down(&self, k: K) -> Option<&Down<K>> {
+		(**self).get_down(k)
+	}
+	#[inline]
+	fn down(&self, k: K) -> &Down<K> {
+		(**self).down(k)
+	}
+
+	#[inline]
+	fn first_root(&self) -> K {
+		(**self).first_root()
+	}
+	#[inline]
+	fn next_root(&self, k: K) -> K {
+		(**self).next_root(k)
+	}
+}
+
+impl<K: Null, S: StorageMut<K> + ?Sized> StorageMut<K> for Box<S> {
+	#[inline]
+	fn get_up_mut(&mut self, k: K) -> Option<&mut Up<K>> {
+		(**self).get_up_mut(k)
+	}
+	#[inline]
+	fn set_up(&mut self, k: K, parent: Up<K>) {
+		(**self).set_up(k, parent)
+	}
+	#[inline]
+	fn up_mut(&mut self, k: K) -> &mut Up<K> {
+		(**self).up_mut(k)
+	}
+	#[inline]
+	fn remove_up(&mut self, k: K) {
+		(**self).remove_up(k)
+	}
+
+	#[inline]
+	fn set_layer(&mut self, k: K, layer: Layer<K>) {
+		(**self).set_layer(k, layer)
+	}
+	#[inline]
+	fn remove_layer(&mut self, k: K) {
+		(**self).remove_layer(k)
+	}
+
+	#[inline]
+	fn get_down_mut(&mut self, k: K) -> Option<&mut Down<K>> {
+		(**self).get_down_mut(k)
+	}
+	#[inline]
+	fn set_down(&mut self, k: K, children: Down<K>) {
+		(**self).set_down(k, children)
+	}
+	#[inline]
+	fn down_mut(&mut self, k: K) -> &mut Down<K> {
+		(**self).down_mut(k)
+	}
+	#[inline]
+	fn remove_down(&mut self, k: K) {
+		(**self).remove_down(k)
+	}
+
+	#[inline]
+	fn set_root(&mut self, k: K) {
+		(**self).set_root(k)
+	}
+	#[inline]
+	fn remove_root(&mut self, k: K) {
+		(**self).remove_root(k)
+	}
+
+	#[inline]
+	fn clear(&mut self) where K: Copy {
+		(**self).clear()
+	}
+
+	#[inline]
+	fn shrink_to_fit(&mut self) {
+		(**self).shrink_to_fit()
+	}
 }
 
 /// 父信息
@@ -57,15 +283,43 @@ pub struct Up<K> {
 	next: K, // 在父节点的子列表中，我的后一个节点
 }
 
+/// Layer中层数的类型，默认为usize
+/// 开启layer_u32特性时为u32，层数很少而节点很多时可以减少Layer占用的内存
+///
+/// 注意：layer_u32不是可叠加（additive）的特性，它会改变Layer::new、Layer::layer等公开接口的类型。
+/// 依赖图中只要有一个crate开启了它，所有依赖本crate的代码看到的都是u32，按usize编写的代码会编译失败，
+/// 因此只应由最终的应用（而不是库）开启；库中应使用LayerIndex而不是具体的整数类型
+#[cfg(not(feature = "layer_u32"))]
+pub type LayerIndex = usize;
+#[cfg(feature = "layer_u32")]
+pub type LayerIndex = u32;
+
+// 层数转换为usize，只有layer_u32时需要转换
+#[cfg(not(feature = "layer_u32"))]
+#[inline]
+fn layer_to_usize(layer: LayerIndex) -> usize {
+	layer
+}
+#[cfg(feature = "layer_u32")]
+#[inline]
+fn layer_to_usize(layer: LayerIndex) -> usize {
+	layer as usize
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Layer<K> {
-	layer: usize,
+	layer: LayerIndex,
 	root: K,
 }
 
 impl<K: Clone + Copy> Layer<K>  {
+	/// 创建Layer，layer为节点所在的层（根节点为1），root为所在树的根节点
+	#[inline]
+	pub fn new(layer: LayerIndex, root: K) -> Self {
+		Layer { layer, root }
+	}
 	#[inline]
-	pub fn layer(&self) -> usize{
+	pub fn layer(&self) -> LayerIndex{
 		self.layer
 	}
 	#[inline]
@@ -77,7 +331,7 @@ impl<K: Clone + Copy> Layer<K>  {
 impl<K: Null> Default for Layer<K>{
 	fn default() -> Self {
 		Layer {
-			layer: usize::null(),
+			layer: LayerIndex::null(),
 			root: K::null(),
 		}
 	}
@@ -160,12 +414,77 @@ impl<K: Null> Default for Down<K> {
 	}
 }
 
-pub struct Tree<K: Null, S> {
+/// 树结构校验失败的原因，node为第一个不满足约束的节点
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeError<K> {
+	/// 子节点的Up::parent不是其所在子节点列表的父节点
+	ParentMismatch { node: K, expected: K, found: K },
+	/// Up::prev与子节点列表中实际的前一个节点不一致
+	PrevMismatch { node: K, expected: K, found: K },
+	/// Down::tail与子节点列表中实际的最后一个节点不一致
+	TailMismatch { node: K, expected: K, found: K },
+	/// Down::len与实际的子节点数量不一致
+	LenMismatch { node: K, expected: usize, found: usize },
+	/// Down::count与实际的递归子节点数量不一致
+	CountMismatch { node: K, expected: usize, found: usize },
+	/// Layer::layer不等于父节点的layer + 1
+	LayerMismatch { node: K, expected: usize, found: usize },
+	/// 子节点列表中的节点没有Up
+	MissingUp { node: K },
+	/// Layer::root与沿父节点向上找到的根节点不一致
+	LayerRootMismatch { node: K, expected: K, found: K },
+	/// 没有父节点的节点在树上，但其Layer不是{layer: 1, root: 自身}，layer、root为实际的值
+	RootMismatch { node: K, layer: usize, root: K },
+}
+
+/// 插入节点失败的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertError<K> {
+	/// 节点与父节点相同
+	SelfParent,
+	/// 节点已经有一个其它的父节点
+	AlreadyParented { existing: K },
+	/// 父节点是该节点的递归子节点，插入后会形成环
+	Cycle,
+}
+
+/// 从父节点数组构建树失败的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParentPointerError<K> {
+	/// 同一个节点出现了多次
+	DuplicateNode { node: K },
+	/// 节点的父节点不在输入中
+	DanglingParent { node: K, parent: K },
+	/// 节点沿父节点向上无法到达根节点（父节点关系形成了环）
+	Cycle { node: K },
+}
+
+/// 节点间相对路径中的一步
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathStep<K> {
+	/// 移动到父节点
+	Up,
+	/// 移动到指定的子节点
+	Down(K),
+}
+
+/// 节点的Up、Down、Layer快照
+#[derive(Debug, Clone)]
+pub struct NodeInfo<K> {
+	pub up: Option<Up<K>>,
+	pub down: Option<Down<K>>,
+	pub layer: Option<Layer<K>>,
+}
+
+pub struct Tree<K: Null, S, L = StructureListener<K>> {
 	storage: S,
 	default_children: Down<K>,
+	counts_frozen: bool, // 为true时，不再向上修改祖先节点的count
+	checks: bool, // 为true时，release模式下也进行插入检查
+	listener: Option<ListenerCell<L>>, // 结构变化的回调，未设置时不产生任何开销
 }
 
-impl<K: Null, S: Storage<K>> Deref for Tree<K, S> {
+impl<K: Null, S: Storage<K>, L> Deref for Tree<K, S, L> {
 	type Target = S;
 
 	fn deref(&self) -> &Self::Target {
@@ -180,494 +499,5684 @@ impl<K: Null, S: Storage<K>> Deref for Tree<K, S> {
 // 	count: 1,
 // }
 
-impl<K: Null + Eq + Clone + Copy, S> Tree<K, S> {
+impl<K: Null + Eq + Clone + Copy, S, L> Tree<K, S, L> {
 	pub fn new(storage: S) -> Self {
 		Self {
 			storage,
 			default_children: Down { head: K::null(), tail: K::null(), len: 0, count: 1 },
+			counts_frozen: false,
+			checks: false,
+			listener: None,
 		}
 	}
 
 	pub fn get_storage(&self) -> &S {
 		&self.storage
 	}
+
+	/// 开启或关闭运行时检查，默认只在debug模式下检查
+	/// 开启后，release模式下插入节点时也会检查节点与父节点相同、节点已有其它父节点、插入后形成环等错误
+	pub fn set_checks(&mut self, enabled: bool) {
+		self.checks = enabled;
+	}
+
+	// 是否需要进行插入检查
+	#[inline]
+	fn checks_enabled(&self) -> bool {
+		cfg!(debug_assertions) || self.checks
+	}
+
+	/// 设置结构变化的回调，插入、移除、移动节点以及子节点顺序变化时调用，会替换之前设置的回调
+	/// 由多个步骤组成的操作（如move_subtree）只通知一次
+	pub fn on_structure_change(&mut self, f: L) {
+		self.listener = Some(ListenerCell(f));
+	}
+
+	/// 取消结构变化的回调
+	pub fn clear_structure_change(&mut self) {
+		self.listener = None;
+	}
+
+	// 执行f期间不发出通知，用于由其它公开操作组成、只需在最后通知一次的操作
+	fn silently<R, F: FnOnce(&mut Self) -> R>(&mut self, f: F) -> R {
+		let listener = self.listener.take();
+		let r = f(self);
+		self.listener = listener;
+		r
+	}
+}
+
+impl<K: Null, S, L: FnMut(StructureEvent<K>)> Tree<K, S, L> {
+	#[inline]
+	fn notify(&mut self, event: StructureEvent<K>) {
+		if let Some(ListenerCell(f)) = &mut self.listener {
+			f(event);
+		}
+	}
 }
 
-impl<K: Null + Eq + Clone + Copy, S: Storage<K>> Tree<K, S> {
-	/// 迭代指定节点的所有子元素
-	pub fn iter(&self, node_children_head: K) -> ChildrenIterator<K, S> {
+impl<K: Null + Eq + Clone + Copy, S: Storage<K>, L> Tree<K, S, L> {
+	/// 迭代指定节点的所有子元素，只有头节点，不知道数量，按需沿兄弟链表前进
+	pub fn iter(&self, node_children_head: K) -> ChildrenIterator<'_, K, S> {
 		ChildrenIterator::new(&self.storage, node_children_head)
 	}
 
-	/// 迭代指定节点的所有递归子元素
-	pub fn recursive_iter(&self, node_children_head: K) -> RecursiveIterator<K, S> {
-		let (head, len) = if node_children_head.is_null() {
-			(K::null(), 0)
-		} else {
-			(node_children_head, 1)
-		};
-		RecursiveIterator::new(&self.storage, head, len)
+	/// 迭代parent的所有子节点，数量直接取自parent的Down，迭代器实现了ExactSizeIterator
+	pub fn children(&self, parent: K) -> ExactChildrenIterator<'_, K, S> {
+		ExactChildrenIterator::from_parent(&self.storage, parent)
 	}
-}
 
-impl<K: Null + Eq + Clone + Copy, S: StorageMut<K>> Tree<K, S> {
+	/// 迭代parent的所有子节点，传入的是父节点而不是子节点列表的头节点，与children相同
+	/// 已经取得头节点时可以直接使用iter(head)
+	pub fn iter_children(&self, parent: K) -> ExactChildrenIterator<'_, K, S> {
+		self.children(parent)
+	}
 
-    /// index为0表示插入到子节点队列前， 如果index大于子节点队列长度，则插入到子节点队列最后。parent如果为0 表示设置为根节点。 如果parent的layer大于0
-	/// order表示在子节点中的顺序，当大于子节点长度时，插入到队列最后
-    pub fn insert_child(&mut self, id: K, parent: K, mut order: usize) {
-		if cfg!(debug_assertions) {
-			if id == parent {
-				panic!("{:?}", pi_print_any::out_any!(format, "insert_child fail, id and parent is equal, id: {:?}, parent: {:?}", id, parent));
-			}
+	/// 迭代parent的所有子节点及其在子节点列表中的序号，序号从0开始，size_hint取自Down::len
+	pub fn children_enumerated(&self, parent: K) -> core::iter::Enumerate<ExactChildrenIterator<'_, K, S>> {
+		self.children(parent).enumerate()
+	}
+
+	/// 迭代parent的子节点，跳过Layer为null（不在树上）的子节点
+	pub fn attached_children(&self, parent: K) -> impl Iterator<Item = K> + '_ {
+		self.children(parent).filter(move |child|{self.is_attached(*child)})
+	}
+
+	/// 取得parent所有子节点的快照，遍历返回值的同时可以修改树
+	pub fn collect_children(&self, parent: K) -> Vec<K> {
+		self.children(parent).collect()
+	}
+
+	/// 先序收集root及其所有递归子节点，按Down::count预分配空间
+	pub fn subtree_keys(&self, root: K) -> Vec<K> {
+		if root.is_null() {
+			return Vec::new();
 		}
+		let mut r = Vec::with_capacity(self.descendant_count(root) + 1);
+		r.push(root);
+		if let Some(down) = self.storage.get_down(root) {
+			r.extend(RecursiveIterator::new(&self.storage, down.head, down.len));
+		}
+		r
+	}
 
-		pi_print_any::out_any!(log::debug, "insert_child, id={:?}, parent={:?}, order={:?}", id, parent, order);
+	/// 先序迭代node所在的子树，包含node自身，不包含node的兄弟节点
+	/// 例如 root -> [a -> [b], c]，recursive_iter(root)依次返回root, a, b, c；recursive_iter(a)返回a, b
+	pub fn recursive_iter(&self, node: K) -> RecursiveIterator<'_, K, S> {
+		RecursiveIterator::from_node(&self.storage, node)
+	}
 
-        if !parent.is_null() {
-            let (p_down, layer) = (
-				// self.storage.get_parent(parent), 
-				self.storage.get_down(parent).unwrap_or(&self.default_children),
-				self.storage.get_layer(parent).map_or(Layer::default(), |layer|{ Layer {layer: if layer.layer.is_null() {usize::null()} else{ layer.layer + 1 }, root: layer.root}})
-			);
+	/// 先序迭代root所在的子树，同时返回每个节点的父节点，root的父节点总是返回null（即使root不是根节点）
+	/// 用于将层级结构打包为父节点数组等扁平数据
+	pub fn iter_with_parent(&self, root: K) -> impl Iterator<Item = (K, K)> + '_ {
+		self.recursive_iter(root).map(move |node|{
+			let parent = if node == root { K::null() } else { self.storage.up(node).parent };
+			(node, parent)
+		})
+	}
 
-			let (prev, next) = if order >= p_down.len {
-				(p_down.tail, K::null())
-			} else if order + order >= p_down.len {
-				// 优化：order顺序在子节点列表中比较靠后，则从最后的位置开始向前寻找对应位置
-				let mut prev = p_down.tail;
-				let mut next = K::null();
-				order = p_down.len - order;
-				while order > 0 && !prev.is_null(){
-					order -= 1;
-					next = prev;
-					prev = self.storage.get_up(next).unwrap().prev;
-				}
-				(prev, next)
-			} else {
-				// 优化：order顺序在子节点列表中比较靠前，则从第一个个位置开始向后寻找对应位置
-				let mut prev = K::null();
-				let mut next = p_down.head;
-				while order > 0 && !next.is_null() {
-					order -= 1;
-					prev = next;
-					next = self.storage.get_up(prev).unwrap().next;
-				}
-				(prev, next)
-			};
-			pi_print_any::out_any!(log::debug, "insert_child1, id={:?}, parent={:?}, prev={:?}, next={:?}, head={:?}, tail={:?}", id, parent, prev, next, p_down.head, p_down.tail);
+	/// 与recursive_iter相同，但使用调用者提供的buf作为迭代的栈，多次迭代复用同一个buf可以避免每次分配内存
+	pub fn recursive_iter_in<'a>(&'a self, node: K, buf: &'a mut Vec<K>) -> RecursiveIterator<'a, K, S> {
+		RecursiveIterator::from_node_in(&self.storage, node, buf)
+	}
 
-            self.insert_node(id, parent, layer, prev, next);
-        } else {
-            self.insert_as_root(id)
-        }
-    }
-    /// 根据InsertType插入到brother的前或后。 brother的layer大于0
-    pub fn insert_brother(&mut self, id: K, brother: K, insert: InsertType) {
-		pi_print_any::out_any!(log::debug, "insert_brother, id={:?}, brother={:?}, insert={:?}", id, brother, &insert);
-        let (parent, layer, prev, next) = match (self.storage.get_up(brother), self.storage.get_layer(brother)) {
-            (Some(up), layer) => match insert {
-                InsertType::Front => (up.parent, layer.map_or(Layer::default(), |l|{l.clone()}), up.prev, brother),
-                InsertType::Back => (up.parent, layer.map_or(Layer::default(), |l|{l.clone()}), brother, up.next),
-            },
-            _ => {
-				out_any!(log::error, "invalid brother: {:?}", brother);
-				panic!("")
+	/// 按先序将以root为根的子树导出为(节点, 父节点, 深度)数组，root的父节点为null、深度为0
+	/// 先序保证父节点总在子节点之前，可以直接按顺序重建（见from_parent_pointers）
+	pub fn to_parent_pointers(&self, root: K) -> Vec<(K, K, usize)> {
+		self.recursive_iter_with_depth(root).map(|(depth, node)|{
+			let parent = if depth == 0 { K::null() } else { self.storage.up(node).parent };
+			(node, parent, depth)
+		}).collect()
+	}
+
+	/// 将以roots中各节点为根的子树按相同的结构（根节点顺序为roots的顺序、子节点顺序不变）重新插入到dst中，返回使用dst存储的树
+	/// 用于在不同的存储之间切换，例如从HashMapTree转换到SlotMapTree；dst应当为空
+	/// 存储不一定记录根节点（Storage::first_root默认为null），因此由调用者给出roots，存储记录了根节点时可以传入tree.roots()收集的结果
+	/// 只复制能从roots到达的节点，已从树上移除的游离节点不会复制；Tree的设置（set_checks、freeze_counts）和结构变化的回调会转移到新的树上
+	pub fn transfer_into<S2: StorageMut<K>>(mut self, dst: S2, roots: &[K]) -> Tree<K, S2, L> {
+		// 插入期间不需要回调，先用不带回调的树重建结构，最后再转移回调
+		let mut tree: Tree<K, S2, fn(StructureEvent<K>)> = Tree::new(dst);
+		tree.checks = self.checks;
+		for root in roots.iter() {
+			if !self.is_root(*root) {
+				panic!("{:?}", out_any!(format, "transfer_into fail, node is not a root, id: {:?}", root));
 			}
-        };
-		pi_print_any::out_any!(log::debug, "insert_brother1, id={:?}, brother={:?}, pre={:?}, next={:?}, head={:?}, tail={:?}, ", id, brother, prev, next, self.storage.get_down(parent).unwrap().head, self.storage.get_down(parent).unwrap().tail);
-		if cfg!(debug_assertions) {
-			if id == parent {
-				panic!("{:?}", pi_print_any::out_any!(format, "insert_brother fail, id and parent is equal, id: {:?}, parent: {:?}", id, parent));
+			// 逆先序，每个节点插入到父节点的子节点列表最前：此时父节点还未链接到树上，插入不需要设置layer，count只累加到父节点
+			let nodes = self.to_parent_pointers(*root);
+			for (node, parent, _) in nodes.iter().skip(1).rev() {
+				tree.insert_child(*node, *parent, 0);
 			}
+			// 最后插入根节点，整棵子树一次设置layer
+			tree.insert_child(*root, K::null(), usize::MAX);
 		}
-        if !parent.is_null() {
-            self.insert_node(id, parent, layer, prev, next)
-        } else {
-            self.insert_as_root(id)
-        }
-    }
-    
-    /// 从树上将节点移除（删除节点上的layer，并设置到正确的节点关联关系、子节点统计数量）
-    pub fn remove(
-        &mut self,
-        id: K,
-    ) {
-		pi_print_any::out_any!(log::debug, "remove, id={:?}", id);
-		// 删除所有递归子节点的layer
-		if let Some(layer) = self.storage.get_layer(id) {
-			if !layer.layer().is_null() {
-				if layer.layer() == 1 {
-					self.storage.remove_root(id);
+		// 插入时没有冻结count，新树的count总是正确的；冻结状态保持不变，解冻时重新计算
+		Tree {
+			storage: tree.storage,
+			default_children: self.default_children,
+			counts_frozen: self.counts_frozen,
+			checks: self.checks,
+			listener: self.listener.take(),
+		}
+	}
+
+	/// 先序迭代node的所有递归子节点，不包含node自身
+	/// 例如 root -> [a -> [b], c]，recursive_iter_children(root)依次返回a, b, c
+	pub fn recursive_iter_children(&self, node: K) -> RecursiveIterator<'_, K, S> {
+		let (head, len) = self.storage.get_down(node).map_or((K::null(), 0), |down|{(down.head, down.len)});
+		RecursiveIterator::new(&self.storage, head, len)
+	}
+
+	/// 先序迭代以root为根的子树，但不超过max_depth层（root为第0层，max_depth为0时只迭代root）
+	pub fn recursive_iter_depth(&self, root: K, max_depth: usize) -> DepthLimitedIterator<'_, K, S> {
+		DepthLimitedIterator::new(&self.storage, root, max_depth)
+	}
+
+	/// 先序迭代以root为根的子树，返回(深度, 节点)，root的深度为0，深度由迭代栈记录，不查询Layer
+	pub fn recursive_iter_with_depth(&self, root: K) -> DepthIterator<'_, K, S> {
+		DepthIterator::new(&self.storage, root)
+	}
+
+	/// 以root为根的子树在depth处的“截面”：先序返回深度恰好为depth的节点，以及深度小于depth的叶子节点（root为第0层）
+	/// 不会遍历深度超过depth的节点，可用于层次细节（LOD）裁剪
+	pub fn frontier(&self, root: K, depth: usize) -> impl Iterator<Item = K> + '_ {
+		let mut iter = self.recursive_iter_depth(root, depth);
+		core::iter::from_fn(move ||{
+			loop {
+				let (d, node) = iter.next_with_depth()?;
+				if d == depth || !self.has_children(node) {
+					return Some(node);
 				}
-				self.remove_tree(self.storage.get_down(id).map_or(K::null(), |down|{down.head}));
+			}
+		})
+	}
+
+	/// 以root为根的子树中，Layer::layer等于depth的节点数量（根节点为第1层，与depth方法一致），不会遍历更深的节点
+	/// root不在树上时，子树中的节点都没有layer，返回0
+	pub fn count_at_depth(&self, root: K, depth: usize) -> usize {
+		let root_layer = match self.depth(root) {
+			Some(layer) if layer <= depth => layer,
+			_ => return 0,
+		};
+		let mut count = 0;
+		let mut arr = vec![(root, root_layer)];
+		while let Some((node, d)) = arr.pop() {
+			if d == depth {
+				count += 1;
+				continue;
+			}
+			let head = self.storage.get_down(node).map_or(K::null(), |down|{down.head});
+			for child in self.iter(head) {
+				arr.push((child, d + 1));
 			}
 		}
+		count
+	}
 
-		if let Some(up)  = self.storage.get_up_mut(id) {
-			if !up.parent.is_null() {
-				let (parent, prev, next) = (up.parent, up.prev, up.next);
-				let count = self.storage.get_down(id).map_or(1, |down|{down.count + 1});
-				self.remove_node(id, parent, count, prev, next);
+	/// 一次遍历统计以root为根的子树中每一层的节点数量，返回值的下标为相对root的深度（root为第0层）
+	/// 下标与Layer::layer的差值为root的层数，root不在树上时也可以统计
+	pub fn depth_histogram(&self, root: K) -> Vec<usize> {
+		let mut r = Vec::new();
+		if root.is_null() {
+			return r;
+		}
+		let mut arr = vec![(root, 0)];
+		while let Some((node, d)) = arr.pop() {
+			if r.len() <= d {
+				r.resize(d + 1, 0);
+			}
+			r[d] += 1;
+			let head = self.storage.get_down(node).map_or(K::null(), |down|{down.head});
+			for child in self.iter(head) {
+				arr.push((child, d + 1));
 			}
 		}
+		r
 	}
 
-    // 插入节点, 如果id就在parent内则为调整位置
-    fn insert_node(
-        &mut self,
-        id: K,
-        parent: K,
-        layer: Layer<K>,
-        prev: K,
-        next: K,
-    ) {
-		// // 调用该方法，该节点可能已经存在，并且是将该节点插入到原位置
-		// // 如果插入到原位置，则无需操作
-		// if id == prev || id == next {
-		// 	return layer;
-		// }
+	/// 后序迭代以root为根的子树（子节点总是先于父节点，root最后迭代）
+	pub fn recursive_iter_post(&self, root: K) -> PostOrderIterator<'_, K, S> {
+		PostOrderIterator::new(&self.storage, root)
+	}
 
-        let (count, fix_prev, fix_next) = match self.storage.get_up_mut(id) {
-            Some(n) if !n.parent.is_null() => {
-				// 当前插入节点已经有一个父节点，并且该节点的父节点与当前指定的兄弟节点的父节点不是同一个
-				// 则panic
-				if n.parent != parent {
-					out_any!(log::error, "has a parent node, id: {:?}, old parent: {:?}, new_parent: {:?}", id, n.parent, parent);
-					panic!("")
-				}
+	/// 按层迭代以root为根的子树（广度优先），第N层的节点总是先于第N+1层的节点
+	pub fn breadth_first_iter(&self, root: K) -> BreadthFirstIterator<'_, K, S> {
+		BreadthFirstIterator::new(&self.storage, root)
+	}
 
-				// 否则，当前节点存在一个父节点，则调整该节点的兄弟节点即可
-				let fix_prev = n.prev;
-				let fix_next = n.next;
-				n.prev = prev;
-				n.next = next;
-				(0, fix_prev, fix_next)
-            }
-            _ => {
-				// 不存在父节，直接挂在树上
-				if !layer.layer.is_null() {
-					self.storage.set_layer(id, layer.clone());
-				}
-				
-				self.storage.set_up(id, Up {
-					parent,
-					prev,
-					next,
-				});
-				self.storage.get_down(id).map_or((1, K::null(), K::null()), |c|{
-					(c.count + 1, c.head, K::null())
-				})
-			},
-		};
-        // 修改prev和next的节点
-        if !prev.is_null() {
-            let mut node = self.storage.up(prev).clone();
-			node.next = id;
-			self.storage.set_up(prev, node);
-        }
-        if !next.is_null() {
-            let mut node = self.storage.up(next).clone();
-            node.prev = id;
-			self.storage.set_up(next, node);
-        }
-        if count == 0 {
-            // 同层调整
-            if !fix_prev.is_null() {
-                let mut node = self.storage.up(fix_prev).clone();
-                node.next = fix_next;
-				self.storage.set_up(fix_prev, node);
-            }
-            if !fix_next.is_null() {
-                let mut node = self.storage.up(fix_next).clone();
-                node.prev = fix_prev;
-				self.storage.set_up(fix_next, node);
-            }
+	/// 迭代以root为根的子树中的所有叶子节点（没有子节点的节点）
+	pub fn leaves(&self, root: K) -> LeafIterator<'_, K, S> {
+		LeafIterator::new(&self.storage, root)
+	}
 
-			
-            if prev.is_null() || next.is_null() || fix_prev.is_null() || fix_next.is_null() {
-                let mut down = self.storage.down(parent).clone();
-                if prev.is_null() {
-                    down.head = id;
-                } else if fix_prev.is_null() {
-                    down.head = fix_next;
-                }
-                if next.is_null() {
-                    down.tail = id;
-                } else if fix_next.is_null() {
-                    down.tail = fix_prev;
-                }
-				self.storage.set_down(parent, down);
-            }
+	/// 按成为根节点的先后顺序迭代森林中的所有根节点，移除根节点不会改变其余根节点的顺序
+	pub fn roots(&self) -> RootsIterator<'_, K, S> {
+		RootsIterator::new(&self.storage)
+	}
+
+	/// 从指定节点的父节点开始，向上迭代所有祖先节点（包含根节点）
+	pub fn ancestors(&self, node: K) -> AncestorIterator<'_, K, S> {
+		AncestorIterator::new(&self.storage, node)
+	}
+
+	/// 从node的父节点开始向上迭代祖先节点，到stop为止（不包含stop）
+	/// stop不是node的祖先节点时（包括stop等于node），返回空迭代器
+	pub fn ancestors_until(&self, node: K, stop: K) -> impl Iterator<Item = K> + '_ {
+		let valid = self.is_ancestor(stop, node);
+		self.ancestors(node).take_while(move |k|{valid && *k != stop})
+	}
+
+	/// 从node开始，依次为其父节点、祖父节点……直到根节点组成的路径
+	pub fn path_to_root(&self, node: K) -> Vec<K> {
+		if node.is_null() {
+			return Vec::new();
+		}
+		// layer即为路径长度，用于预分配
+		let cap = self.depth(node).unwrap_or(1);
+		let mut r = Vec::with_capacity(cap);
+		r.push(node);
+		r.extend(self.ancestors(node));
+		r
+	}
+
+	/// 从根节点到node的路径，为path_to_root的逆序
+	pub fn path_from_root(&self, node: K) -> Vec<K> {
+		let mut r = self.path_to_root(node);
+		r.reverse();
+		r
+	}
+
+	/// 从from到to的相对路径：先若干Up到达最近公共祖先，再若干Down到达to，不在同一棵树上时返回None
+	pub fn relative_path(&self, from: K, to: K) -> Option<Vec<PathStep<K>>> {
+		let lca = self.lowest_common_ancestor(from, to)?;
+		let lca_depth = self.depth(lca)?;
+		let up_count = self.depth(from)? - lca_depth;
+		let down_count = self.depth(to)? - lca_depth;
+
+		let mut r = Vec::with_capacity(up_count + down_count);
+		r.resize(up_count, PathStep::Up);
+		// 从to向上收集到lca之前的节点，再逆序追加
+		let start = r.len();
+		let mut node = to;
+		for _ in 0..down_count {
+			r.push(PathStep::Down(node));
+			node = self.storage.up(node).parent;
+		}
+		r[start..].reverse();
+		Some(r)
+	}
+
+	/// 迭代指定节点的所有兄弟节点（不包含自身），根节点没有兄弟节点
+	pub fn siblings(&self, node: K) -> SiblingIterator<'_, K, S> {
+		SiblingIterator::new(&self.storage, self.siblings_head(node), node)
+	}
+
+	/// 迭代指定节点的所有兄弟节点（包含自身）
+	pub fn siblings_with_self(&self, node: K) -> SiblingIterator<'_, K, S> {
+		SiblingIterator::new(&self.storage, self.siblings_head(node), K::null())
+	}
+
+	/// 父节点，根节点或不在树上的节点返回None
+	pub fn parent(&self, node: K) -> Option<K> {
+		self.storage.get_up(node).map(|up|{up.parent}).filter(|k|{!k.is_null()})
+	}
+
+	/// 第一个子节点
+	pub fn first_child(&self, node: K) -> Option<K> {
+		self.storage.get_down(node).map(|down|{down.head}).filter(|k|{!k.is_null()})
+	}
+
+	/// 最后一个子节点
+	pub fn last_child(&self, node: K) -> Option<K> {
+		self.storage.get_down(node).map(|down|{down.tail}).filter(|k|{!k.is_null()})
+	}
+
+	/// 沿Down::head向下，直到没有子节点的节点，node本身没有子节点时返回node
+	pub fn first_leaf(&self, mut node: K) -> K {
+		while let Some(child) = self.first_child(node) {
+			node = child;
+		}
+		node
+	}
+
+	/// 沿Down::tail向下，直到没有子节点的节点，node本身没有子节点时返回node
+	pub fn last_leaf(&self, mut node: K) -> K {
+		while let Some(child) = self.last_child(node) {
+			node = child;
+		}
+		node
+	}
+
+	/// 下一个兄弟节点
+	pub fn next_sibling(&self, node: K) -> Option<K> {
+		self.storage.get_up(node).map(|up|{up.next}).filter(|k|{!k.is_null()})
+	}
+
+	/// 上一个兄弟节点
+	pub fn prev_sibling(&self, node: K) -> Option<K> {
+		self.storage.get_up(node).map(|up|{up.prev}).filter(|k|{!k.is_null()})
+	}
+
+	/// 先序遍历中node的下一个节点，只在node所在的树中查找，node为树中最后一个节点时返回None
+	/// 有子节点时为第一个子节点，否则为自身或最近的祖先的下一个兄弟节点
+	pub fn next_preorder(&self, node: K) -> Option<K> {
+		if let Some(child) = self.first_child(node) {
+			return Some(child);
+		}
+		let mut node = node;
+		loop {
+			if let Some(next) = self.next_sibling(node) {
+				return Some(next);
+			}
+			node = self.parent(node)?;
+		}
+	}
+
+	/// 先序遍历中node之后第一个与node同层的节点，可以跨越不同的父节点，只在node所在的树中查找
+	/// 只根据父子链接计算相对深度，不查询Layer；不会进入比node更深的子树
+	pub fn next_same_layer(&self, node: K) -> Option<K> {
+		self.same_layer(node, true)
+	}
+
+	/// 先序遍历中node之前最后一个与node同层的节点，与next_same_layer方向相反
+	pub fn prev_same_layer(&self, node: K) -> Option<K> {
+		self.same_layer(node, false)
+	}
+
+	fn same_layer(&self, node: K, forward: bool) -> Option<K> {
+		if node.is_null() {
+			return None;
+		}
+		// d为当前节点相对node的深度
+		let (mut cur, mut d) = (node, 0isize);
+		loop {
+			let child = if d < 0 {
+				if forward { self.first_child(cur) } else { self.last_child(cur) }
+			} else {
+				None
+			};
+			match child {
+				Some(child) => {
+					cur = child;
+					d += 1;
+				},
+				None => loop {
+					let sibling = if forward { self.next_sibling(cur) } else { self.prev_sibling(cur) };
+					if let Some(sibling) = sibling {
+						cur = sibling;
+						break;
+					}
+					cur = self.parent(cur)?;
+					d -= 1;
+				},
+			}
+			if d == 0 {
+				return Some(cur);
+			}
+		}
+	}
+
+	/// 先序遍历中node的上一个节点，只在node所在的树中查找，node为根节点时返回None
+	/// 有前一个兄弟节点时为该兄弟节点的last_leaf，否则为父节点
+	pub fn prev_preorder(&self, node: K) -> Option<K> {
+		match self.prev_sibling(node) {
+			Some(prev) => Some(self.last_leaf(prev)),
+			None => self.parent(node),
+		}
+	}
+
+	/// 从第一个子节点开始向后查找，返回第一个满足pred的子节点
+	pub fn find_child<F: FnMut(K) -> bool>(&self, parent: K, mut pred: F) -> Option<K> {
+		let mut id = self.storage.get_down(parent).map_or(K::null(), |down|{down.head});
+		while !id.is_null() {
+			if pred(id) {
+				return Some(id);
+			}
+			id = self.storage.up(id).next;
+		}
+		None
+	}
+
+	/// 从最后一个子节点开始向前查找，返回第一个满足pred的子节点
+	pub fn rfind_child<F: FnMut(K) -> bool>(&self, parent: K, mut pred: F) -> Option<K> {
+		let mut id = self.storage.get_down(parent).map_or(K::null(), |down|{down.tail});
+		while !id.is_null() {
+			if pred(id) {
+				return Some(id);
+			}
+			id = self.storage.up(id).prev;
+		}
+		None
+	}
+
+	/// 从node的父节点开始向上查找，返回第一个满足pred的祖先节点，不包含node自身，到根节点仍不满足时返回None
+	/// 需要包含node自身时，先对node调用pred即可
+	pub fn find_ancestor<F: FnMut(K) -> bool>(&self, node: K, mut pred: F) -> Option<K> {
+		let mut id = self.storage.get_up(node).map_or(K::null(), |up|{up.parent});
+		while !id.is_null() {
+			if pred(id) {
+				return Some(id);
+			}
+			id = self.storage.get_up(id).map_or(K::null(), |up|{up.parent});
+		}
+		None
+	}
+
+	/// 第index个子节点（从0开始），index不小于子节点数量时返回None
+	pub fn nth_child(&self, parent: K, index: usize) -> Option<K> {
+		let down = self.storage.get_down(parent)?;
+		if index >= down.len {
+			return None;
+		}
+		if index + index >= down.len {
+			// 优化：index比较靠后，则从最后的位置开始向前寻找
+			let mut id = down.tail;
+			for _ in 0..down.len - 1 - index {
+				id = self.storage.up(id).prev;
+			}
+			Some(id)
+		} else {
+			let mut id = down.head;
+			for _ in 0..index {
+				id = self.storage.up(id).next;
+			}
+			Some(id)
+		}
+	}
+
+	/// 节点在父节点子节点列表中的位置（从0开始），根节点或不在树上的节点返回None
+	pub fn index_in_parent(&self, node: K) -> Option<usize> {
+		let up = self.storage.get_up(node)?;
+		if up.parent.is_null() {
+			return None;
+		}
+		let mut index = 0;
+		let mut id = up.prev;
+		while !id.is_null() {
+			index += 1;
+			id = self.storage.up(id).prev;
+		}
+		Some(index)
+	}
+
+	/// 森林中挂在根节点下的节点总数（包含根节点自身），由各根节点的Down::count累加得到，复杂度为根节点数量
+	/// 已从树上移除但仍保留Up/Down的游离节点不计入
+	pub fn len(&self) -> usize {
+		self.roots().map(|root|{self.descendant_count(root) + 1}).sum()
+	}
+
+	/// 森林中是否没有根节点，与len() == 0、roots()为空一致
+	pub fn is_empty(&self) -> bool {
+		self.storage.first_root().is_null()
+	}
+
+	/// 节点所在兄弟列表的长度（包含自身），即父节点的Down::len
+	/// 根节点没有兄弟，但自身算一个，返回1；既没有父节点也不在树上的节点返回0
+	pub fn sibling_count(&self, node: K) -> usize {
+		match self.parent(node) {
+			Some(parent) => self.child_count(parent),
+			None if self.is_attached(node) => 1,
+			None => 0,
+		}
+	}
+
+	/// a和b是否为兄弟节点：两者都在树上、不是同一个节点，且有相同的父节点
+	/// 根节点没有父节点，两个根节点不视为兄弟节点（与sibling_count对根节点返回1一致）
+	pub fn are_siblings(&self, a: K, b: K) -> bool {
+		if a == b || !self.is_attached(a) || !self.is_attached(b) {
+			return false;
+		}
+		match (self.parent(a), self.parent(b)) {
+			(Some(pa), Some(pb)) => pa == pb,
+			_ => false,
+		}
+	}
+
+	/// 节点是否有子节点（Down::len > 0），Down存在但len为0时返回false
+	pub fn has_children(&self, node: K) -> bool {
+		self.child_count(node) > 0
+	}
+
+	/// 节点是否为叶子节点：在树上且没有子节点
+	pub fn is_leaf(&self, node: K) -> bool {
+		self.is_attached(node) && !self.has_children(node)
+	}
+
+	/// 直接子节点的数量（Down::len），没有Down时为0
+	pub fn child_count(&self, node: K) -> usize {
+		self.storage.get_down(node).map_or(0, |down|{down.len})
+	}
+
+	/// 递归子节点的数量，不包含节点自身，没有Down时为0
+	/// 注意：Down::count本身就不包含节点自身，因此这里直接返回Down::count
+	pub fn descendant_count(&self, node: K) -> usize {
+		self.storage.get_down(node).map_or(0, |down|{down.count})
+	}
+
+	/// remove(id)（或drain_subtree）会涉及的节点数量，包含节点自身，即Down::count + 1；节点不存在时为0
+	pub fn remove_preview_count(&self, id: K) -> usize {
+		if self.exists(id) {
+			self.descendant_count(id) + 1
+		} else {
+			0
+		}
+	}
+
+	/// 节点所在的层（根节点为1），节点不在树上时返回None
+	pub fn depth(&self, node: K) -> Option<usize> {
+		match self.storage.get_layer(node) {
+			Some(layer) if !layer.layer.is_null() => Some(layer_to_usize(layer.layer)),
+			_ => None,
+		}
+	}
+
+	/// 节点是否在树上（有非null的Layer，即可以从某个根节点到达）
+	pub fn is_attached(&self, node: K) -> bool {
+		self.storage.get_layer(node).is_some_and(|layer|{!layer.layer.is_null()})
+	}
+
+	/// 节点是否为根节点：Layer::layer为1，且Layer::root为节点自身（insert_as_root建立的约束）
+	pub fn is_root(&self, node: K) -> bool {
+		self.storage.get_layer(node).is_some_and(|layer|{layer.layer == 1 && layer.root == node})
+	}
+
+	/// 一次取得节点的Up、Down、Layer，三者都不存在时返回None
+	pub fn node_info(&self, node: K) -> Option<NodeInfo<K>> {
+		let info = NodeInfo {
+			up: self.storage.get_up(node).copied(),
+			down: self.storage.get_down(node).copied(),
+			layer: self.storage.get_layer(node).cloned(),
+		};
+		if info.up.is_none() && info.down.is_none() && info.layer.is_none() {
+			None
+		} else {
+			Some(info)
+		}
+	}
+
+	/// 节点是否存在于存储中（有Up或Down），游离的节点也返回true
+	pub fn exists(&self, node: K) -> bool {
+		self.storage.get_up(node).is_some() || self.storage.get_down(node).is_some()
+	}
+
+	/// 节点所在树的根节点，节点不在树上时返回None
+	pub fn root_of(&self, node: K) -> Option<K> {
+		match self.storage.get_layer(node) {
+			Some(layer) if !layer.layer.is_null() => Some(layer.root),
+			_ => None,
+		}
+	}
+
+	/// ancestor是否为descendant的祖先节点（节点不是自身的祖先）
+	pub fn is_ancestor(&self, ancestor: K, descendant: K) -> bool {
+		if ancestor == descendant {
+			return false;
+		}
+		// 两个节点都在树上时，利用layer快速排除
+		if let (Some(a), Some(d)) = (self.storage.get_layer(ancestor), self.storage.get_layer(descendant)) {
+			if !a.layer.is_null() && !d.layer.is_null() && (a.root != d.root || a.layer >= d.layer) {
+				return false;
+			}
+		}
+		self.ancestors(descendant).any(|k| k == ancestor)
+	}
+
+	/// 多个节点的最近公共祖先，即包含所有节点的最小子树的根，依次对每个节点求lowest_common_ancestor
+	/// nodes为空、有节点不在树上或不在同一棵树上时返回None；只有一个节点时返回该节点
+	pub fn common_ancestor(&self, nodes: &[K]) -> Option<K> {
+		let (first, rest) = nodes.split_first()?;
+		if !self.is_attached(*first) {
+			return None;
+		}
+		rest.iter().try_fold(*first, |lca, node|{self.lowest_common_ancestor(lca, *node)})
+	}
+
+	/// 两个节点的最近公共祖先（若一个节点是另一个的祖先，则返回该节点）
+	/// 两个节点不在同一棵树上，或有节点不在树上时返回None
+	pub fn lowest_common_ancestor(&self, a: K, b: K) -> Option<K> {
+		let (la, lb) = match (self.storage.get_layer(a), self.storage.get_layer(b)) {
+			(Some(la), Some(lb)) if !la.layer.is_null() && !lb.layer.is_null() && la.root == lb.root => (la.layer, lb.layer),
+			_ => return None,
+		};
+		let (mut a, mut b) = (a, b);
+		// 将较深的节点向上移动到同一层
+		for _ in lb..la {
+			a = self.storage.up(a).parent;
+		}
+		for _ in la..lb {
+			b = self.storage.up(b).parent;
+		}
+		// 同步向上，直到相遇
+		while a != b {
+			a = self.storage.up(a).parent;
+			b = self.storage.up(b).parent;
+		}
+		Some(a)
+	}
+
+	/// 校验存储记录的所有根节点的树（见Storage::first_root），返回第一个不满足约束的节点，约束与validate_subtree相同
+	/// 存储不记录根节点时没有可校验的树，总是返回Ok，此时应对每棵树调用validate_subtree
+	pub fn validate(&self) -> Result<(), TreeError<K>> {
+		for root in self.roots() {
+			self.validate_subtree(root)?;
+		}
+		Ok(())
+	}
+
+	/// 校验以root为根的树的结构，返回第一个不满足约束的节点
+	/// 检查的约束：Up::parent、Up::prev与子节点列表一致，Down::tail、Down::len与实际子节点一致，
+	/// Down::count等于实际的递归子节点数量，Layer::layer等于父节点的layer + 1（父节点不在树上时，子节点也不应在树上），
+	/// root没有父节点且在树上时，必须满足is_root，树上每个节点的Layer::root等于沿父节点向上找到的根节点
+	pub fn validate_subtree(&self, root: K) -> Result<(), TreeError<K>> {
+		if self.parent(root).is_none() && !self.is_root(root) {
+			if let Some(layer) = self.depth(root) {
+				return Err(TreeError::RootMismatch { node: root, layer, root: self.storage.layer(root).root });
+			}
+		}
+		let mut top = root;
+		while let Some(parent) = self.parent(top) {
+			top = parent;
+		}
+		// 后序遍历，校验父节点时，子节点的count已经校验过
+		for node in self.recursive_iter_post(root) {
+			if let Some(found) = self.root_of(node) {
+				if found != top {
+					return Err(TreeError::LayerRootMismatch { node, expected: top, found });
+				}
+			}
+			let down = match self.storage.get_down(node) {
+				Some(down) => down,
+				None => continue,
+			};
+			let layer = self.depth(node).map_or(usize::null(), |l|{l + 1});
+			let (mut prev, mut len, mut count) = (K::null(), 0, 0);
+			let mut child = down.head;
+			while !child.is_null() {
+				let up = match self.storage.get_up(child) {
+					Some(up) => up,
+					None => return Err(TreeError::MissingUp { node: child }),
+				};
+				if up.parent != node {
+					return Err(TreeError::ParentMismatch { node: child, expected: node, found: up.parent });
+				}
+				if up.prev != prev {
+					return Err(TreeError::PrevMismatch { node: child, expected: prev, found: up.prev });
+				}
+				let child_layer = self.depth(child).unwrap_or(usize::null());
+				if child_layer != layer {
+					return Err(TreeError::LayerMismatch { node: child, expected: layer, found: child_layer });
+				}
+				len += 1;
+				count += self.descendant_count(child) + 1;
+				prev = child;
+				child = up.next;
+			}
+			if down.tail != prev {
+				return Err(TreeError::TailMismatch { node, expected: prev, found: down.tail });
+			}
+			if down.len != len {
+				return Err(TreeError::LenMismatch { node, expected: len, found: down.len });
+			}
+			if down.count != count {
+				return Err(TreeError::CountMismatch { node, expected: count, found: down.count });
+			}
+		}
+		Ok(())
+	}
+
+	/// 以缩进文本输出以root为根的子树，每个节点一行，文字由label返回，用于日志和测试快照，例如：
+	/// ```text
+	/// root
+	/// ├── a
+	/// │   └── c
+	/// └── b
+	/// ```
+	pub fn format_subtree<F: Fn(K) -> String>(&self, root: K, label: F) -> String {
+		let mut out = String::new();
+		// open[i]表示第i + 1层的祖先节点之后是否还有兄弟节点，决定该列输出竖线还是空白
+		let mut open: Vec<bool> = Vec::new();
+		for (depth, node) in self.recursive_iter_with_depth(root) {
+			if depth > 0 {
+				open.truncate(depth - 1);
+				for o in open.iter() {
+					out.push_str(if *o { "│   " } else { "    " });
+				}
+				let is_last = self.storage.up(node).next.is_null();
+				out.push_str(if is_last { "└── " } else { "├── " });
+				open.push(!is_last);
+			}
+			out.push_str(&label(node));
+			out.push('\n');
+		}
+		out
+	}
+
+	/// 以Graphviz DOT格式输出roots下的所有树，用于调试
+	/// 每个节点以label返回的文字及其layer标注，父节点到每个子节点有一条边
+	#[cfg(feature = "std")]
+	pub fn to_dot<W: Write, F: Fn(K) -> String>(&self, roots: &[K], w: &mut W, label: F) -> io::Result<()> where K: Debug {
+		let id = |k: K| format!("{:?}", k).replace('"', "\\\"");
+		writeln!(w, "digraph tree {{")?;
+		for root in roots.iter() {
+			for node in self.recursive_iter_depth(*root, usize::MAX) {
+				let layer = self.depth(node).map_or("null".to_string(), |l|{l.to_string()});
+				writeln!(w, "    \"{}\" [label=\"{} (layer {})\"];", id(node), label(node).replace('"', "\\\""), layer)?;
+				for child in self.iter(self.storage.get_down(node).map_or(K::null(), |down|{down.head})) {
+					writeln!(w, "    \"{}\" -> \"{}\";", id(node), id(child))?;
+				}
+			}
+		}
+		writeln!(w, "}}")
+	}
+
+	// 取到节点所在子节点列表的头节点
+	fn siblings_head(&self, node: K) -> K {
+		match self.storage.get_up(node) {
+			Some(up) if !up.parent.is_null() => self.storage.get_down(up.parent).map_or(K::null(), |down|{down.head}),
+			_ => K::null(),
+		}
+	}
+}
+
+// pi_slotmap不支持no_std，只在std下提供
+#[cfg(feature = "std")]
+impl<K: Null + Eq + Clone + Copy + Key, S: Storage<K>, L> Tree<K, S, L> {
+	/// 以深度优先的进入、退出顺序为root子树中的每个节点分配区间(enter, exit)
+	/// enter为节点的先序序号，exit为其子树中最后一个节点的先序序号，可配合interval_contains在O(1)内判断祖先关系
+	/// 区间只在树结构不变时有效，修改树之后需要重新计算
+	pub fn compute_intervals(&self, root: K) -> SecondaryMap<K, (u32, u32)> {
+		let mut r = SecondaryMap::with_capacity(self.descendant_count(root) + 1);
+		if root.is_null() {
+			return r;
+		}
+		let mut time = 0u32;
+		let mut arr = vec![(root, false)];
+		while let Some((node, exit)) = arr.pop() {
+			if exit {
+				if let Some(iv) = r.get_mut(node) {
+					iv.1 = time - 1;
+				}
+				continue;
+			}
+			r.insert(node, (time, time));
+			time += 1;
+			arr.push((node, true));
+			let head = self.storage.get_down(node).map_or(K::null(), |down|{down.head});
+			for child in self.iter(head) {
+				arr.push((child, false));
+			}
+		}
+		r
+	}
+}
+
+/// 区间parent是否包含区间child（区间由Tree::compute_intervals计算），节点的区间包含自身
+#[inline]
+pub fn interval_contains(parent: (u32, u32), child: (u32, u32)) -> bool {
+	parent.0 <= child.0 && child.1 <= parent.1
+}
+
+#[cfg(feature = "rayon")]
+impl<K: Null + Eq + Clone + Copy + Send + Sync, S: Storage<K> + Sync, L> Tree<K, S, L> {
+	/// 并行遍历指定节点的所有递归子节点
+	/// 每个直接子节点所在的子树作为一个rayon任务，任务内按先序对子树中的每个节点调用f
+	pub fn par_for_each_child_subtree<F: Fn(K) + Sync>(&self, parent: K, f: F) {
+		let head = match self.storage.get_down(parent) {
+			Some(down) => down.head,
+			None => return,
+		};
+		let f = &f;
+		rayon::scope(|scope| {
+			for child in self.iter(head) {
+				scope.spawn(move |_| {
+					for k in self.recursive_iter(child) {
+						f(k);
+					}
+				});
+			}
+		});
+	}
+}
+
+impl<K: Null + Eq + Clone + Copy, S: StorageMut<K>, L: FnMut(StructureEvent<K>)> Tree<K, S, L> {
+
+    /// index为0表示插入到子节点队列前， 如果index大于子节点队列长度，则插入到子节点队列最后。parent如果为0 表示设置为根节点。 如果parent的layer大于0
+	/// order表示在子节点中的顺序，当大于子节点长度时，插入到队列最后
+	/// 插入失败（见try_insert_child）时panic；但parent为null而id已有父节点时，与原来一样只输出错误日志，不做任何修改
+    pub fn insert_child(&mut self, id: K, parent: K, order: usize) {
+		// 与insert_as_root一致：已有父节点的节点不能设置为根节点，只输出错误日志
+		if parent.is_null() {
+			if let Some(old) = self.parent(id) {
+				out_any!(log::error, "insert_root fail, node has a parent, id: {:?}, parent: {:?}", id, old);
+				return;
+			}
+		}
+		// 环的检查需要向上遍历，只在debug模式或开启了set_checks时进行
+		if let Err(e) = self.insert_child_checked(id, parent, order, self.checks_enabled()) {
+			panic!("{:?}", out_any!(format, "insert_child fail, id: {:?}, parent: {:?}, err: {:?}", id, parent, e));
+		}
+	}
+
+	/// 从子节点列表的末尾计算插入位置：插入后id之后恰好有offset个兄弟节点
+	/// offset为0表示插入到队列最后，offset大于等于子节点队列长度时插入到队列最前
+	/// 例如子节点为[a, b, c]时，offset为1得到[a, b, id, c]，offset为3得到[id, a, b, c]
+	/// 换算为insert_child的order（len - offset），靠后的位置会从尾节点开始向前查找
+	pub fn insert_child_from_back(&mut self, id: K, parent: K, offset: usize) {
+		let len = self.child_count(parent);
+		self.insert_child(id, parent, len - offset.min(len));
+	}
+
+	/// 同insert_child，但插入失败时返回错误，树不做任何修改
+	/// * id与parent相同时，返回InsertError::SelfParent
+	/// * id已经有一个其它的父节点时，返回InsertError::AlreadyParented
+	/// * parent是id的递归子节点时，返回InsertError::Cycle
+	pub fn try_insert_child(&mut self, id: K, parent: K, order: usize) -> Result<(), InsertError<K>> {
+		self.insert_child_checked(id, parent, order, true)
+	}
+
+	fn insert_child_checked(&mut self, id: K, parent: K, order: usize, check_cycle: bool) -> Result<(), InsertError<K>> {
+		if id == parent {
+			return Err(InsertError::SelfParent);
+		}
+		if let Some(up) = self.storage.get_up(id) {
+			if !up.parent.is_null() && up.parent != parent {
+				return Err(InsertError::AlreadyParented { existing: up.parent });
+			}
+		}
+		if check_cycle && !parent.is_null() && self.is_ancestor(id, parent) {
+			return Err(InsertError::Cycle);
+		}
+
+		out_any!(log::debug, "insert_child, id={:?}, parent={:?}, order={:?}", id, parent, order);
+
+		// 已经是parent的子节点时，只是调整在子节点列表中的位置
+		let reorder = !parent.is_null() && self.storage.get_up(id).is_some_and(|up|{up.parent == parent});
+        if !parent.is_null() {
+            let (p_down, layer) = (
+				// self.storage.get_parent(parent), 
+				self.storage.get_down(parent).unwrap_or(&self.default_children),
+				self.storage.get_layer(parent).map_or(Layer::default(), |layer|{ Layer {layer: if layer.layer.is_null() {LayerIndex::null()} else{ layer.layer + 1 }, root: layer.root}})
+			);
+
+			let (prev, next) = self.child_links(p_down, order);
+			out_any!(log::debug, "insert_child1, id={:?}, parent={:?}, prev={:?}, next={:?}, head={:?}, tail={:?}", id, parent, prev, next, p_down.head, p_down.tail);
+
+            self.insert_node(id, parent, layer, prev, next);
+        } else {
+            self.insert_as_root(id)
         }
-		// 修改parent的children, count
-		let mut p_down = self.storage.get_down(parent).map_or(Down::default(), |c|{c.clone()});
-		if prev.is_null() {
-			p_down.head = id;
+		if reorder {
+			self.notify(StructureEvent::Reordered { parent });
+		} else {
+			self.notify(StructureEvent::Attached { node: id, parent });
+		}
+		Ok(())
+    }
+
+	// 根据order在p_down的子节点列表中找到插入位置的前后节点
+	fn child_links(&self, p_down: &Down<K>, mut order: usize) -> (K, K) {
+		if order >= p_down.len {
+			(p_down.tail, K::null())
+		} else if order + order >= p_down.len {
+			// 优化：order顺序在子节点列表中比较靠后，则从最后的位置开始向前寻找对应位置
+			let mut prev = p_down.tail;
+			let mut next = K::null();
+			order = p_down.len - order;
+			while order > 0 && !prev.is_null(){
+				order -= 1;
+				next = prev;
+				prev = self.storage.get_up(next).unwrap().prev;
+			}
+			(prev, next)
+		} else {
+			// 优化：order顺序在子节点列表中比较靠前，则从第一个个位置开始向后寻找对应位置
+			let mut prev = K::null();
+			let mut next = p_down.head;
+			while order > 0 && !next.is_null() {
+				order -= 1;
+				prev = next;
+				next = self.storage.get_up(prev).unwrap().next;
+			}
+			(prev, next)
+		}
+	}
+
+	/// 将children作为一段连续的兄弟节点，批量插入到parent的子节点列表末尾
+	/// 与逐个insert_child相比，parent的Down只修改一次，祖先的count也只向上修改一次
+	/// children中的节点不能已经有父节点，不能重复，不能是parent或parent的祖先，否则panic（panic时树没有被修改）
+	pub fn insert_children(&mut self, parent: K, children: &[K]) {
+		out_any!(log::debug, "insert_children, parent={:?}, len={:?}", parent, children.len());
+		if parent.is_null() {
+			for id in children.iter() {
+				self.insert_as_root(*id);
+				self.notify(StructureEvent::Attached { node: *id, parent });
+			}
+			return;
+		}
+		if children.is_empty() {
+			return;
+		}
+
+		let mut p_down = self.storage.get_down(parent).map_or(Down::default(), |c|{*c});
+		let layer = self.storage.get_layer(parent).map_or(Layer::default(), |layer|{ Layer {layer: if layer.layer.is_null() {LayerIndex::null()} else{ layer.layer + 1 }, root: layer.root}});
+
+		// 先检查并链接每个节点的Up，链接后节点的parent不为null，children中重复的节点会被当作已有父节点的节点检查出来
+		// 没有父节点的节点只可能是parent所在树的最顶层节点的祖先，因此只需要与该节点比较
+		let top = self.ancestors(parent).last().unwrap_or(parent);
+		let mut olds = Vec::with_capacity(children.len());
+		let mut prev = p_down.tail;
+		for (i, id) in children.iter().enumerate() {
+			let id = *id;
+			let old = self.storage.get_up(id).copied();
+			let cycle = id == parent || id == top;
+			let has_parent = old.is_some_and(|up|{!up.parent.is_null()});
+			if cycle || has_parent {
+				// 恢复已链接的节点，保证panic时树没有被修改
+				for (k, up) in children.iter().zip(olds) {
+					match up {
+						Some(up) => self.storage.set_up(*k, up),
+						None => self.storage.remove_up(*k),
+					}
+				}
+				if cycle {
+					panic!("{:?}", out_any!(format, "insert_children fail, node is parent or an ancestor of parent, id: {:?}, parent: {:?}", id, parent));
+				}
+				panic!("{:?}", out_any!(format, "insert_children fail, node has a parent or is repeated in children, id: {:?}, parent: {:?}", id, parent));
+			}
+			olds.push(old);
+			let next = children.get(i + 1).map_or(K::null(), |k|{*k});
+			self.storage.set_up(id, Up {parent, prev, next});
+			prev = id;
+		}
+
+		let mut count = 0;
+		for id in children.iter() {
+			let id = *id;
+			self.unroot(id, &layer);
+			let head = match self.storage.get_down(id) {
+				Some(down) => {
+					count += down.count + 1;
+					down.head
+				},
+				None => {
+					count += 1;
+					K::null()
+				}
+			};
+			if layer.layer.is_null() {
+				self.storage.remove_layer(id);
+			} else {
+				self.insert_tree(head, Layer {layer: layer.layer + 1, root: layer.root});
+				self.storage.set_layer(id, layer.clone());
+			}
+		}
+
+		// 与原来的尾节点相连
+
+		if p_down.tail.is_null() {
+			p_down.head = children[0];
+		} else {
+			self.storage.up_mut(p_down.tail).next = children[0];
+		}
+		p_down.tail = prev;
+		p_down.len += children.len();
+		p_down.count += count;
+		self.storage.set_down(parent, p_down);
+
+		let p_p = self.storage.get_up(parent).map_or(K::null(), |p|{p.parent});
+		self.modify_count(p_p, count as isize);
+		if self.listener.is_some() {
+			for id in children.iter() {
+				self.notify(StructureEvent::Attached { node: *id, parent });
+			}
+		}
+	}
+
+	/// 将id链接到parent的子节点列表中prev与next之间，并直接使用传入的layer，不会遍历子树重新计算layer
+	/// 用于加载已知每个节点层数和根节点的树（如反序列化），比insert_child少一次子树遍历
+	/// 调用者负责正确性：parent不能为null，prev、next必须是parent的相邻子节点（或null表示列表头尾），
+	/// layer必须等于parent的layer + 1，id的子树中已有的layer也必须正确；可以在加载完成后调用validate检查
+	pub fn insert_node_with_layer(&mut self, id: K, parent: K, layer: Layer<K>, prev: K, next: K) {
+		out_any!(log::debug, "insert_node_with_layer, id={:?}, parent={:?}, prev={:?}, next={:?}", id, parent, prev, next);
+		// 传入null的layer，insert_node只做链接而不会遍历子树
+		self.insert_node(id, parent, Layer::default(), prev, next);
+		if !layer.layer.is_null() {
+			self.storage.set_layer(id, layer);
+		}
+		self.notify(StructureEvent::Attached { node: id, parent });
+	}
+
+    /// 根据InsertType插入到brother的前或后。 brother的layer大于0
+    pub fn insert_brother(&mut self, id: K, brother: K, insert: InsertType) {
+		out_any!(log::debug, "insert_brother, id={:?}, brother={:?}, insert={:?}", id, brother, &insert);
+        let (parent, layer, prev, next) = match (self.storage.get_up(brother), self.storage.get_layer(brother)) {
+            (Some(up), layer) => match insert {
+                InsertType::Front => (up.parent, layer.map_or(Layer::default(), |l|{l.clone()}), up.prev, brother),
+                InsertType::Back => (up.parent, layer.map_or(Layer::default(), |l|{l.clone()}), brother, up.next),
+            },
+            _ => {
+				out_any!(log::error, "invalid brother: {:?}", brother);
+				panic!("")
+			}
+        };
+		out_any!(log::debug, "insert_brother1, id={:?}, brother={:?}, pre={:?}, next={:?}, head={:?}, tail={:?}, ", id, brother, prev, next, self.storage.get_down(parent).unwrap().head, self.storage.get_down(parent).unwrap().tail);
+		if self.checks_enabled() {
+			if id == parent {
+				panic!("{:?}", out_any!(format, "insert_brother fail, id and parent is equal, id: {:?}, parent: {:?}", id, parent));
+			}
+			if !parent.is_null() && self.is_ancestor(id, parent) {
+				panic!("{:?}", out_any!(format, "insert_brother fail, parent is in the subtree of id, id: {:?}, parent: {:?}", id, parent));
+			}
+		}
+		let reorder = !parent.is_null() && self.storage.get_up(id).is_some_and(|up|{up.parent == parent});
+        if !parent.is_null() {
+            self.insert_node(id, parent, layer, prev, next)
+        } else {
+            self.insert_as_root(id)
+        }
+		if reorder {
+			self.notify(StructureEvent::Reordered { parent });
+		} else {
+			self.notify(StructureEvent::Attached { node: id, parent });
+		}
+    }
+    
+	/// 确保id是一个根节点：已经是根节点时什么也不做（不会输出错误日志），否则作为根节点插入
+	pub fn ensure_root(&mut self, id: K) {
+		match self.storage.get_layer(id) {
+			Some(layer) if layer.layer == 1 && layer.root == id => (),
+			_ => self.insert_child(id, K::null(), usize::MAX),
+		}
+	}
+
+	/// 统一的插入入口，根据target分别调用insert_child、insert_before、insert_after
+	pub fn insert(&mut self, id: K, target: Target<K>) {
+		match target {
+			Target::Root => self.insert_child(id, K::null(), 0),
+			Target::ChildOf { parent, order } => self.insert_child(id, parent, order),
+			Target::Before(reference) => self.insert_before(id, reference),
+			Target::After(reference) => self.insert_after(id, reference),
+		}
+	}
+
+	/// 按root子树的结构复制一个新的子树，每个节点的新key由new_key(旧key)分配，返回新子树的根
+	/// 新子树不在任何树上（与detach后的状态相同），可以通过insert_child挂到需要的位置
+	pub fn clone_subtree<F: FnMut(K) -> K>(&mut self, root: K, mut new_key: F) -> K {
+		out_any!(log::debug, "clone_subtree, root={:?}", root);
+		if root.is_null() {
+			return K::null();
+		}
+		let new_root = new_key(root);
+		let mut arr = vec![(root, new_root)];
+		// 新子树不在树上，复制过程不发出通知
+		self.silently(|tree|{
+			while let Some((old, new)) = arr.pop() {
+				let children = tree.collect_children(old);
+				for child in children {
+					let new_child = new_key(child);
+					tree.insert_child(new_child, new, usize::MAX);
+					arr.push((child, new_child));
+				}
+			}
+		});
+		new_root
+	}
+
+	/// 将id插入到reference之前，reference必须有父节点
+	pub fn insert_before(&mut self, id: K, reference: K) {
+		self.check_attached(reference);
+		self.insert_brother(id, reference, InsertType::Front)
+	}
+
+	/// 将id插入到reference之后，reference必须有父节点
+	pub fn insert_after(&mut self, id: K, reference: K) {
+		self.check_attached(reference);
+		self.insert_brother(id, reference, InsertType::Back)
+	}
+
+	fn check_attached(&self, reference: K) {
+		if self.storage.get_up(reference).is_none_or(|up|{up.parent.is_null()}) {
+			panic!("{:?}", out_any!(format, "reference is not attached to a parent, reference: {:?}", reference));
+		}
+	}
+
+	/// 清空整棵树
+	pub fn clear(&mut self) {
+		out_any!(log::debug, "clear");
+		self.storage.clear();
+	}
+
+	/// 大量删除节点后，释放存储中的多余容量（存储不支持时什么也不做，见StorageMut::shrink_to_fit）
+	pub fn shrink_to_fit(&mut self) {
+		self.storage.shrink_to_fit();
+	}
+
+    /// 从树上将节点移除（删除节点上的layer，并设置到正确的节点关联关系、子节点统计数量）
+	/// 注意：节点自身的Down以及递归子节点的Up、Down会保留，之后再次插入该节点时，整个子树会随之重新挂到树上
+	/// 如果需要彻底删除子树的结构数据，使用remove_recursive
+    pub fn remove(
+        &mut self,
+        id: K,
+    ) {
+		out_any!(log::debug, "remove, id={:?}", id);
+		let mut detached = None;
+		// 删除所有递归子节点的layer
+		if let Some(layer) = self.storage.get_layer(id) {
+			if !layer.layer().is_null() {
+				if layer.layer() == 1 {
+					detached = Some(K::null());
+					self.storage.remove_root(id);
+					self.storage.remove_layer(id);
+				}
+				self.remove_tree(self.storage.get_down(id).map_or(K::null(), |down|{down.head}));
+			}
+		} else {
+			// detach断开的子树，子节点上保留了断开前的layer，需要删除
+			let head = self.storage.get_down(id).map_or(K::null(), |down|{down.head});
+			if !head.is_null() && self.is_attached(head) {
+				self.remove_tree(head);
+			}
+		}
+
+		if let Some(up)  = self.storage.get_up_mut(id) {
+			if !up.parent.is_null() {
+				let (parent, prev, next) = (up.parent, up.prev, up.next);
+				let count = self.storage.get_down(id).map_or(1, |down|{down.count + 1});
+				self.remove_node(id, parent, count, prev, next);
+				detached = Some(parent);
+			}
+		}
+		if let Some(parent) = detached {
+			self.notify(StructureEvent::Detached { node: id, parent });
+		}
+	}
+
+	/// 批量移除ids中的节点，与逐个调用remove相同，但ids中某个节点的祖先也在ids中时，该节点会随祖先一起移除而不再单独处理
+	/// ids按层数从小到大处理，祖先移除后其子树中的节点不再在树上，重复的id和不在树上的id都会被跳过
+	/// 返回所有被移除的节点（包括被移除节点的递归子节点），每个被移除的子树按先序排列
+	pub fn remove_many(&mut self, ids: &[K]) -> Vec<K> {
+		out_any!(log::debug, "remove_many, len={:?}", ids.len());
+		let mut sorted: Vec<(LayerIndex, K)> = ids.iter().filter_map(|id|{
+			self.storage.get_layer(*id).filter(|layer|{!layer.layer.is_null()}).map(|layer|{(layer.layer, *id)})
+		}).collect();
+		sorted.sort_by_key(|(layer, _)|{*layer});
+		let mut removed = Vec::new();
+		for (_, id) in sorted {
+			if !self.is_attached(id) {
+				continue;
+			}
+			removed.extend(self.recursive_iter(id));
+			self.remove(id);
+		}
+		removed
+	}
+
+	/// 移除parent的所有直接子节点，每个子节点的状态与remove之后相同（子树结构保留，layer被删除）
+	/// parent的Down被清空，祖先节点的count只向上修改一次
+	pub fn remove_children(&mut self, parent: K) {
+		out_any!(log::debug, "remove_children, parent={:?}", parent);
+		let down = match self.storage.get_down(parent) {
+			Some(down) if down.len > 0 => *down,
+			_ => return,
+		};
+		let mut child = down.head;
+		while !child.is_null() {
+			let next = self.storage.up(child).next;
+			if self.is_attached(child) {
+				self.storage.remove_layer(child);
+				self.remove_tree(self.storage.get_down(child).map_or(K::null(), |down|{down.head}));
+			}
+			self.storage.remove_up(child);
+			self.notify(StructureEvent::Detached { node: child, parent });
+			child = next;
+		}
+		self.storage.set_down(parent, Down::default());
+
+		let p_p = self.storage.get_up(parent).map_or(K::null(), |up|{up.parent});
+		self.modify_count(p_p, -(down.count as isize));
+	}
+
+	/// 从树上移除节点，并删除节点及其所有递归子节点的Up、Down、Layer
+	pub fn remove_recursive(&mut self, id: K) {
+		out_any!(log::debug, "remove_recursive, id={:?}", id);
+		self.drain_subtree(id);
+	}
+
+	/// 与remove_recursive相同，但按后序返回被删除的节点（子节点先于父节点，root最后），方便调用者依次释放外部数据
+	pub fn drain_subtree(&mut self, root: K) -> Vec<K> {
+		out_any!(log::debug, "drain_subtree, root={:?}", root);
+		let keys: Vec<K> = self.recursive_iter_post(root).collect();
+		self.remove(root);
+		for k in keys.iter() {
+			self.storage.remove_up(*k);
+			self.storage.remove_down(*k);
+			self.storage.remove_layer(*k);
+		}
+		keys
+	}
+
+	/// 删除节点（Up、Down、Layer），并将它的子节点按原顺序插入到它在父节点子列表中的位置
+	/// 被提升的子树层数都减1，父节点及其祖先的count减1；节点为根节点时，它的每个子节点都成为根节点
+	/// 节点已断开（不是根节点，也没有父节点）时，它的每个子节点都成为断开的子树，不会成为根节点
+	pub fn remove_and_promote_children(&mut self, id: K) {
+		out_any!(log::debug, "remove_and_promote_children, id={:?}", id);
+		if self.listener.is_none() {
+			return self.promote_children(id);
+		}
+		let parent = self.storage.get_up(id).map_or(K::null(), |up|{up.parent});
+		let linked = !parent.is_null() || self.storage.get_layer(id).is_some_and(|layer|{layer.layer == 1});
+		let children = self.collect_children(id);
+		self.silently(|tree|{tree.promote_children(id)});
+		if !linked {
+			return;
+		}
+		self.notify(StructureEvent::Detached { node: id, parent });
+		for child in children {
+			self.notify(StructureEvent::Reparented { node: child, old_parent: id, new_parent: parent });
+		}
+	}
+
+	fn promote_children(&mut self, id: K) {
+		let (parent, prev, next) = match self.storage.get_up(id) {
+			Some(up) if !up.parent.is_null() => (up.parent, up.prev, up.next),
+			_ => {
+				let children = self.collect_children(id);
+				if self.is_root(id) {
+					self.remove(id);
+					for child in children {
+						self.remove(child);
+						self.insert_child(child, K::null(), usize::MAX);
+					}
+				} else {
+					// 已断开的节点，子节点同样成为断开的子树，不加入根节点列表
+					for child in children {
+						self.storage.remove_up(child);
+					}
+					self.storage.remove_up(id);
+					self.storage.remove_layer(id);
+				}
+				self.storage.remove_down(id);
+				return;
+			}
+		};
+		let down = match self.storage.get_down(id) {
+			Some(down) if down.len > 0 => *down,
+			_ => {
+				self.remove(id);
+				self.storage.remove_down(id);
+				return;
+			}
+		};
+
+		// 先在原位置上修改子树的layer，此时子节点链表的尾部还没有与后面的兄弟节点相连
+		match self.storage.get_layer(id) {
+			Some(layer) if !layer.layer.is_null() => {
+				let layer = layer.clone();
+				self.insert_tree(down.head, layer);
+			},
+			_ => (),
+		}
+
+		let mut child = down.head;
+		while !child.is_null() {
+			let up = self.storage.up_mut(child);
+			up.parent = parent;
+			child = up.next;
+		}
+		self.storage.up_mut(down.head).prev = prev;
+		self.storage.up_mut(down.tail).next = next;
+		let p_down = self.storage.down_mut(parent);
+		if prev.is_null() {
+			p_down.head = down.head;
+		}
+		if next.is_null() {
+			p_down.tail = down.tail;
+		}
+		p_down.len = p_down.len + down.len - 1;
+		p_down.count -= 1;
+		if !prev.is_null() {
+			self.storage.up_mut(prev).next = down.head;
+		}
+		if !next.is_null() {
+			self.storage.up_mut(next).prev = down.tail;
+		}
+		let p_p = self.storage.get_up(parent).map_or(K::null(), |up|{up.parent});
+		self.modify_count(p_p, -1);
+
+		self.storage.remove_up(id);
+		self.storage.remove_down(id);
+		self.storage.remove_layer(id);
+	}
+
+	/// 将节点从父节点上断开，但保留子树内部的结构和layer，之后可以通过reattach将整个子树重新挂回
+	/// 与remove不同，detach不遍历子树，重新挂到同一层时reattach也不需要遍历子树
+	/// 断开后的状态：
+	/// * 节点自身：没有Up和Layer（不在树上，也不再是根节点），Down保留（仍指向原来的子节点）
+	/// * 递归子节点：Up、Down保留，Layer也保留断开前的值（已过时，depth、is_attached等仍按断开前的位置返回）
+	/// * 原父节点及其祖先：子节点列表和count已修正
+	///
+	/// 断开的子树应通过reattach挂回，或通过remove清除保留的layer
+	pub fn detach(&mut self, id: K) {
+		out_any!(log::debug, "detach, id={:?}", id);
+		let parent = match self.storage.get_up(id) {
+			Some(up) if !up.parent.is_null() => {
+				let (parent, prev, next) = (up.parent, up.prev, up.next);
+				let count = self.storage.get_down(id).map_or(1, |down|{down.count + 1});
+				self.remove_node(id, parent, count, prev, next);
+				parent
+			},
+			_ => {
+				if !self.is_root(id) {
+					return;
+				}
+				self.storage.remove_root(id);
+				self.storage.remove_layer(id);
+				K::null()
+			}
+		};
+		self.notify(StructureEvent::Detached { node: id, parent });
+	}
+
+	/// 将节点连同其子树移动到new_parent下，order的含义与insert_child相同
+	/// 子树内部的链接关系保持不变，新旧父节点及其祖先的count会被正确修改，子树中每个节点的layer只重新设置一次
+	pub fn move_subtree(&mut self, id: K, new_parent: K, order: usize) {
+		out_any!(log::debug, "move_subtree, id={:?}, new_parent={:?}, order={:?}", id, new_parent, order);
+		// 必须在断开之前检查，否则失败时树已经被修改
+		if !new_parent.is_null() && (id == new_parent || self.is_ancestor(id, new_parent)) {
+			out_any!(log::error, "move_subtree fail, new_parent is in the subtree of id, id: {:?}, new_parent: {:?}", id, new_parent);
+			return;
+		}
+		let old_parent = self.storage.get_up(id).map_or(K::null(), |up|{up.parent});
+		// 从原来的父节点上断开（子树的layer暂时保留，插入时会被覆盖）
+		match self.storage.get_up(id) {
+			Some(up) if !up.parent.is_null() => {
+				let (parent, prev, next) = (up.parent, up.prev, up.next);
+				let count = self.storage.get_down(id).map_or(1, |down|{down.count + 1});
+				self.remove_node(id, parent, count, prev, next);
+			},
+			_ => {
+				if let Some(layer) = self.storage.get_layer(id) {
+					if layer.layer() == 1 {
+						self.storage.remove_root(id);
+					}
+				}
+				self.storage.remove_layer(id);
+			}
+		}
+
+		self.silently(|tree|{tree.insert_child(id, new_parent, order)});
+
+		// 新的父节点不在树上，则插入时不会重新设置子树的layer，需要删除子树上残留的layer
+		if self.depth(id).is_none() {
+			self.remove_tree(self.storage.get_down(id).map_or(K::null(), |down|{down.head}));
+		}
+		self.notify(StructureEvent::Reparented { node: id, old_parent, new_parent });
+	}
+
+	/// 用new替换old在树中的位置，old的Up、Down、Layer转移给new，old的直接子节点改为以new为父节点
+	/// new必须是不在树上的新节点；old为根节点时，new成为新的根节点（排在根节点列表最后）
+	pub fn replace_node(&mut self, old: K, new: K) {
+		out_any!(log::debug, "replace_node, old={:?}, new={:?}", old, new);
+		if old == new {
+			return;
+		}
+		if self.checks_enabled() && self.exists(new) {
+			panic!("{:?}", out_any!(format, "replace_node fail, new node already exists, old: {:?}, new: {:?}", old, new));
+		}
+
+		let parent = self.storage.get_up(old).map_or(K::null(), |up|{up.parent});
+		let linked = !parent.is_null() || self.storage.get_layer(old).is_some_and(|layer|{layer.layer == 1});
+		if let Some(up) = self.storage.get_up(old).copied() {
+			self.storage.remove_up(old);
+			self.storage.set_up(new, up);
+			if !up.parent.is_null() {
+				self.link_siblings(new);
+			}
+		}
+
+		if let Some(down) = self.storage.get_down(old).copied() {
+			self.storage.remove_down(old);
+			self.storage.set_down(new, down);
+			let mut child = down.head;
+			while !child.is_null() {
+				let up = self.storage.up_mut(child);
+				up.parent = new;
+				child = up.next;
+			}
+		}
+
+		if let Some(layer) = self.storage.get_layer(old).cloned() {
+			self.storage.remove_layer(old);
+			if !layer.layer.is_null() && layer.root == old {
+				// 替换的是根节点，整棵树的Layer::root都需要修改
+				self.storage.remove_root(old);
+				self.storage.set_root(new);
+				self.reset_layer(new, Layer {layer: 1, root: new});
+			} else {
+				self.storage.set_layer(new, layer);
+			}
+		}
+		if linked {
+			self.notify(StructureEvent::Detached { node: old, parent });
+			self.notify(StructureEvent::Attached { node: new, parent });
+		}
+	}
+
+	/// 根据父子关系重新计算root及其递归子节点的Layer，用于直接修改存储后的修复
+	/// root有父节点时沿用父节点的Layer，没有父节点时，记录在根节点列表中则作为根节点，否则视为不在树上并删除子树的Layer
+	pub fn recompute_layers(&mut self, root: K) {
+		let parent = self.storage.get_up(root).map_or(K::null(), |up|{up.parent});
+		let layer = if !parent.is_null() {
+			match self.storage.get_layer(parent) {
+				Some(l) if !l.layer.is_null() => Layer {layer: l.layer + 1, root: l.root},
+				_ => Layer::default(),
+			}
+		} else if self.roots().any(|r|{r == root}) {
+			Layer {layer: 1, root}
+		} else {
+			Layer::default()
+		};
+		self.reset_layer(root, layer);
+	}
+
+	/// 自底向上重新计算root及其递归子节点的Down::count，用于直接修改存储后的修复
+	/// 只修改以root为根的子树，root的祖先节点不会修改，需要时应对整棵树的根节点调用
+	pub fn recompute_counts(&mut self, root: K) {
+		// 后序遍历保证子节点的count先于父节点计算
+		let nodes: Vec<K> = self.recursive_iter_post(root).collect();
+		for node in nodes {
+			let head = match self.storage.get_down(node) {
+				Some(down) => down.head,
+				None => continue,
+			};
+			let count = self.iter(head).map(|child|{self.descendant_count(child) + 1}).sum();
+			self.storage.down_mut(node).count = count;
+		}
+	}
+
+	/// 将节点连同其子树挂到new_parent下，order的含义与insert_child相同
+	/// 与move_subtree不同，节点在树上或是被detach断开的子树时，会利用子树原有的layer减少遍历：
+	/// * 新位置的层数与根节点都不变，不遍历子树
+	/// * 层数不变但根节点改变，只修改子树中每个节点的Layer::root
+	/// * 层数改变时，与insert_child一样重新设置整个子树的layer
+	///
+	/// 被detach断开的节点自身没有layer，原来的层数由第一个子节点保留的layer得到；被remove移除的子树没有保留layer，总是重新设置
+	pub fn reattach(&mut self, id: K, new_parent: K, order: usize) {
+		out_any!(log::debug, "reattach, id={:?}, new_parent={:?}, order={:?}", id, new_parent, order);
+		if new_parent.is_null() {
+			self.move_subtree(id, new_parent, order);
+			return;
+		}
+		if self.checks_enabled() && (id == new_parent || self.is_ancestor(id, new_parent)) {
+			panic!("{:?}", out_any!(format, "reattach fail, new_parent is in the subtree of id, id: {:?}, new_parent: {:?}", id, new_parent));
+		}
+
+		let own = match self.storage.get_layer(id) {
+			Some(layer) if !layer.layer.is_null() => Some(layer.clone()),
+			_ => None,
+		};
+		let old = own.clone().or_else(|| {
+			// detach断开的子树，子节点保留了断开前的layer
+			let head = self.storage.get_down(id).map_or(K::null(), |down|{down.head});
+			match self.storage.get_layer(head) {
+				Some(layer) if !head.is_null() && !layer.layer.is_null() => Some(Layer {layer: layer.layer - 1, root: layer.root}),
+				_ => None,
+			}
+		});
+		let old_parent = self.storage.get_up(id).map_or(K::null(), |up|{up.parent});
+		// 从原来的父节点上断开，保留子树的layer
+		match self.storage.get_up(id) {
+			Some(up) if !up.parent.is_null() => {
+				let (parent, prev, next) = (up.parent, up.prev, up.next);
+				let count = self.storage.get_down(id).map_or(1, |down|{down.count + 1});
+				self.remove_node(id, parent, count, prev, next);
+			},
+			_ => {
+				if own.is_some_and(|layer|{layer.layer == 1}) {
+					self.storage.remove_root(id);
+				}
+			}
+		}
+
+		let layer = self.storage.get_layer(new_parent).map_or(Layer::default(), |layer|{ Layer {layer: if layer.layer.is_null() {LayerIndex::null()} else{ layer.layer + 1 }, root: layer.root}});
+		let p_down = self.storage.get_down(new_parent).unwrap_or(&self.default_children);
+		let (prev, next) = self.child_links(p_down, order);
+		match old {
+			Some(old) if !layer.layer.is_null() && old.layer == layer.layer => {
+				// 层数不变，链接时不遍历子树（断开时已删除节点自身的layer，需要重新设置）
+				self.insert_node(id, new_parent, Layer::default(), prev, next);
+				self.storage.set_layer(id, layer.clone());
+				if old.root != layer.root {
+					let keys: Vec<K> = self.recursive_iter(id).collect();
+					for k in keys {
+						self.storage.set_layer(k, Layer {layer: self.storage.layer(k).layer, root: layer.root});
+					}
+				}
+			},
+			Some(_) if layer.layer.is_null() => {
+				// 新的父节点不在树上，删除子树上残留的layer
+				self.insert_node(id, new_parent, layer, prev, next);
+				self.reset_layer(id, Layer::default());
+			},
+			_ => self.insert_node(id, new_parent, layer, prev, next),
+		}
+		self.notify(StructureEvent::Reparented { node: id, old_parent, new_parent });
+	}
+
+	/// 冻结count：之后的插入、删除等操作只修改直接父节点的Down::count，不再逐层向上修改祖先节点
+	/// 用于大量批量修改，冻结期间各节点的count不正确，需要调用unfreeze_counts重新计算
+	pub fn freeze_counts(&mut self) {
+		self.counts_frozen = true;
+	}
+
+	/// 解除冻结，并对所有根节点所在的树自底向上重新计算一次count
+	/// 冻结期间从树上移除的子树不会被重新计算
+	pub fn unfreeze_counts(&mut self) {
+		if !self.counts_frozen {
+			return;
+		}
+		self.counts_frozen = false;
+		let roots: Vec<K> = self.roots().collect();
+		for root in roots {
+			self.recompute_counts(root);
+		}
+	}
+
+	/// 将节点与其前一个兄弟节点交换位置，节点已经是第一个子节点（或没有父节点）时什么也不做
+	pub fn move_prev(&mut self, node: K) {
+		out_any!(log::debug, "move_prev, node={:?}", node);
+		if let Some(prev) = self.prev_sibling(node) {
+			self.swap_with_next(prev, node);
+		}
+	}
+
+	/// 将节点与其后一个兄弟节点交换位置，节点已经是最后一个子节点（或没有父节点）时什么也不做
+	pub fn move_next(&mut self, node: K) {
+		out_any!(log::debug, "move_next, node={:?}", node);
+		if let Some(next) = self.next_sibling(node) {
+			self.swap_with_next(node, next);
+		}
+	}
+
+	// 交换相邻的兄弟节点a和b（b为a的下一个节点），只修改链接，layer和count不变
+	fn swap_with_next(&mut self, a: K, b: K) {
+		let up_a = *self.storage.up(a);
+		let (prev, next) = (up_a.prev, self.storage.up(b).next);
+		*self.storage.up_mut(b) = Up {parent: up_a.parent, prev, next: a};
+		*self.storage.up_mut(a) = Up {parent: up_a.parent, prev: b, next};
+		self.link_siblings(a);
+		self.link_siblings(b);
+		self.notify(StructureEvent::Reordered { parent: up_a.parent });
+	}
+
+	/// 交换两个节点在各自父节点子列表中的位置，子树跟随节点一起移动
+	/// 两个节点都必须有父节点，且不能互为祖先
+	pub fn swap(&mut self, a: K, b: K) {
+		out_any!(log::debug, "swap, a={:?}, b={:?}", a, b);
+		if a == b {
+			return;
+		}
+		let (up_a, up_b) = match (self.storage.get_up(a), self.storage.get_up(b)) {
+			(Some(up_a), Some(up_b)) if !up_a.parent.is_null() && !up_b.parent.is_null() => (*up_a, *up_b),
+			_ => {
+				out_any!(log::error, "swap fail, node has no parent, a: {:?}, b: {:?}", a, b);
+				return;
+			}
+		};
+		if self.is_ancestor(a, b) || self.is_ancestor(b, a) {
+			out_any!(log::error, "swap fail, one node is the ancestor of the other, a: {:?}, b: {:?}", a, b);
+			return;
+		}
+
+		// 相邻节点需要特殊处理，否则两个节点会互相指向自身
+		let (new_a, new_b) = if up_a.next == b {
+			(Up {parent: up_a.parent, prev: b, next: up_b.next}, Up {parent: up_a.parent, prev: up_a.prev, next: a})
+		} else if up_b.next == a {
+			(Up {parent: up_a.parent, prev: up_b.prev, next: b}, Up {parent: up_a.parent, prev: a, next: up_a.next})
+		} else {
+			(up_b, up_a)
+		};
+		self.storage.set_up(a, new_a);
+		self.storage.set_up(b, new_b);
+		self.link_siblings(a);
+		self.link_siblings(b);
+
+		if up_a.parent != up_b.parent {
+			// 父节点不同，两条祖先链上的count需要修改
+			let count_a = self.storage.get_down(a).map_or(1, |down|{down.count + 1}) as isize;
+			let count_b = self.storage.get_down(b).map_or(1, |down|{down.count + 1}) as isize;
+			if count_a != count_b {
+				self.modify_count(up_a.parent, count_b - count_a);
+				self.modify_count(up_b.parent, count_a - count_b);
+			}
+
+			// 所在层或根不同，需要重新设置子树的layer
+			let layer_a = self.storage.get_layer(a).map_or(Layer::default(), |l|{l.clone()});
+			let layer_b = self.storage.get_layer(b).map_or(Layer::default(), |l|{l.clone()});
+			if layer_a.layer != layer_b.layer || layer_a.root != layer_b.root {
+				self.reset_layer(a, layer_b);
+				self.reset_layer(b, layer_a);
+			}
+			self.notify(StructureEvent::Reparented { node: a, old_parent: up_a.parent, new_parent: up_b.parent });
+			self.notify(StructureEvent::Reparented { node: b, old_parent: up_b.parent, new_parent: up_a.parent });
+		} else {
+			self.notify(StructureEvent::Reordered { parent: up_a.parent });
+		}
+	}
+
+	/// 原地反转指定节点的子节点顺序，不影响layer和count
+	pub fn reverse_children(&mut self, parent: K) {
+		let head = match self.storage.get_down_mut(parent) {
+			Some(down) => {
+				let head = down.head;
+				mem::swap(&mut down.head, &mut down.tail);
+				head
+			},
+			None => return,
+		};
+		let mut id = head;
+		while !id.is_null() {
+			let up = self.storage.up_mut(id);
+			let next = up.next;
+			mem::swap(&mut up.next, &mut up.prev);
+			id = next;
+		}
+		self.notify(StructureEvent::Reordered { parent });
+	}
+
+	/// 使用比较函数对指定节点的子节点稳定排序，不影响layer和count
+	pub fn sort_children_by<F: FnMut(K, K) -> Ordering>(&mut self, parent: K, mut cmp: F) {
+		let head = match self.storage.get_down(parent) {
+			Some(down) => down.head,
+			None => return,
+		};
+		let mut children: Vec<K> = self.iter(head).collect();
+		children.sort_by(|a, b| cmp(*a, *b));
+		self.relink_children(parent, &children);
+	}
+
+	/// 按order的顺序一次性重新链接parent的子节点列表，不影响layer和count
+	/// order必须是parent当前子节点的一个排列，否则panic；检查时临时将已检查节点的parent置为null来标记重复节点，只需O(n)
+	pub fn set_child_order(&mut self, parent: K, order: &[K]) {
+		out_any!(log::debug, "set_child_order, parent={:?}, len={:?}", parent, order.len());
+		if order.len() != self.child_count(parent) {
+			panic!("{:?}", out_any!(format, "set_child_order fail, len mismatch, parent: {:?}, expected: {:?}, found: {:?}", parent, self.child_count(parent), order.len()));
+		}
+		// 长度相同时，order中每个节点都是parent的子节点且不重复，即为一个排列
+		let mut checked = 0;
+		while checked < order.len() && self.parent(order[checked]) == Some(parent) {
+			self.storage.up_mut(order[checked]).parent = K::null();
+			checked += 1;
+		}
+		for id in order[..checked].iter() {
+			self.storage.up_mut(*id).parent = parent;
+		}
+		if checked < order.len() {
+			panic!("{:?}", out_any!(format, "set_child_order fail, node is not a child of parent or is repeated, id: {:?}, parent: {:?}", order[checked], parent));
+		}
+		if order.is_empty() {
+			return;
+		}
+		self.relink_children(parent, order);
+	}
+
+	// 按照children的顺序，重新链接parent的子节点列表
+	// 安全：调用该方法，确保children中的节点正是parent的全部子节点
+	fn relink_children(&mut self, parent: K, children: &[K]) {
+		let mut prev = K::null();
+		for (i, id) in children.iter().enumerate() {
+			let up = self.storage.up_mut(*id);
+			up.prev = prev;
+			up.next = children.get(i + 1).map_or(K::null(), |k|{*k});
+			prev = *id;
+		}
+		let down = self.storage.down_mut(parent);
+		down.head = children.first().map_or(K::null(), |k|{*k});
+		down.tail = prev;
+		self.notify(StructureEvent::Reordered { parent });
+	}
+
+	// 根据节点的up，将前后兄弟节点（或父节点的head、tail）指向该节点
+	fn link_siblings(&mut self, id: K) {
+		let up = *self.storage.up(id);
+		if up.prev.is_null() {
+			self.storage.down_mut(up.parent).head = id;
+		} else {
+			self.storage.up_mut(up.prev).next = id;
+		}
+		if up.next.is_null() {
+			self.storage.down_mut(up.parent).tail = id;
+		} else {
+			self.storage.up_mut(up.next).prev = id;
+		}
+	}
+
+	// 重新设置节点及其递归子节点的layer，layer为null时删除子树上的layer
+	fn reset_layer(&mut self, id: K, layer: Layer<K>) {
+		let head = self.storage.get_down(id).map_or(K::null(), |down|{down.head});
+		if layer.layer.is_null() {
+			self.storage.remove_layer(id);
+			self.remove_tree(head);
+		} else {
+			self.insert_tree(head, Layer {layer: layer.layer + 1, root: layer.root});
+			self.storage.set_layer(id, layer);
+		}
+	}
+
+    // 插入节点, 如果id就在parent内则为调整位置
+    fn insert_node(
+        &mut self,
+        id: K,
+        parent: K,
+        layer: Layer<K>,
+        prev: K,
+        next: K,
+    ) {
+        let (count, fix_prev, fix_next) = match self.storage.get_up_mut(id) {
+            Some(n) if !n.parent.is_null() => {
+				// 当前插入节点已经有一个父节点，并且该节点的父节点与当前指定的兄弟节点的父节点不是同一个
+				// 则panic
+				if n.parent != parent {
+					out_any!(log::error, "has a parent node, id: {:?}, old parent: {:?}, new_parent: {:?}", id, n.parent, parent);
+					panic!("")
+				}
+
+				// 插入到原位置，则无需调整（否则节点会指向自身）
+				if id == prev || id == next {
+					return;
+				}
+
+				// 否则，当前节点存在一个父节点，则调整该节点的兄弟节点即可
+				let fix_prev = n.prev;
+				let fix_next = n.next;
+				n.prev = prev;
+				n.next = next;
+				(0, fix_prev, fix_next)
+            }
+            _ => {
+				// 不存在父节，直接挂在树上
+				self.unroot(id, &layer);
+				if !layer.layer.is_null() {
+					self.storage.set_layer(id, layer.clone());
+				}
+				
+				self.storage.set_up(id, Up {
+					parent,
+					prev,
+					next,
+				});
+				self.storage.get_down(id).map_or((1, K::null(), K::null()), |c|{
+					(c.count + 1, c.head, K::null())
+				})
+			},
+		};
+        // 修改prev和next的节点
+        if !prev.is_null() {
+            self.storage.up_mut(prev).next = id;
+        }
+        if !next.is_null() {
+            self.storage.up_mut(next).prev = id;
+        }
+        if count == 0 {
+            // 同层调整
+            if !fix_prev.is_null() {
+                self.storage.up_mut(fix_prev).next = fix_next;
+            }
+            if !fix_next.is_null() {
+                self.storage.up_mut(fix_next).prev = fix_prev;
+            }
+
+			
+            if prev.is_null() || next.is_null() || fix_prev.is_null() || fix_next.is_null() {
+                let mut down = self.storage.down(parent).clone();
+                if prev.is_null() {
+                    down.head = id;
+                } else if fix_prev.is_null() {
+                    down.head = fix_next;
+                }
+                if next.is_null() {
+                    down.tail = id;
+                } else if fix_next.is_null() {
+                    down.tail = fix_prev;
+                }
+				self.storage.set_down(parent, down);
+            }
+			// 同层调整，子节点数量和count不变
+			return;
+        }
+		// 修改parent的children, count（parent没有Down时从count为0开始，count不包含parent自身）
+		let mut p_down = self.storage.get_down(parent).map_or(Down::default(), |c|{c.clone()});
+		if prev.is_null() {
+			p_down.head = id;
+		}
+		if next.is_null() {
+			p_down.tail = id;
+		}
+		p_down.len += 1;
+		p_down.count += count;
+		self.storage.set_down(parent, p_down);
+
+		let p_p = self.storage.get_up(parent).map_or(K::null(), |p|{p.parent});
+		// 递归向上修改count
+		self.modify_count(p_p, count as isize);
+
+		// layer.layer.is_null()时，才递归设置layer
+        if !layer.layer.is_null() {
+            self.insert_tree(fix_prev, Layer {layer: layer.layer + 1, root: layer.root.clone()});
+			// 再次设置当前节点的layer，表明该节点是作为挂在主树上的一个子树的根
+			self.storage.set_layer(id, layer);
+		}
+    }
+
+	// 根节点被挂到其它节点下时，从根节点列表中删除；新的父节点不在树上（layer为null）时，同时删除子树上残留的layer
+	fn unroot(&mut self, id: K, layer: &Layer<K>) {
+		if self.is_root(id) {
+			self.storage.remove_root(id);
+			if layer.layer.is_null() {
+				self.storage.remove_layer(id);
+				self.remove_tree(self.storage.get_down(id).map_or(K::null(), |down|{down.head}));
+			}
+		}
+	}
+
+	/// 创建一个根节点
+	fn insert_as_root(&mut self, id: K) {
+        // 设置为根节点
+		match self.storage.get_up(id) {
+			// 将节点作为根节点插入到树失败，节点已经存在一个父
+			Some(up) if !up.parent.is_null() => {
+				out_any!(log::error, "insert_root fail, node has a parent, id: {:?}, parent: {:?}", id, up.parent);
+				return;
+			},
+			_ => {
+				self.storage.set_root(id);
+				self.storage.set_layer(id, Layer {layer: 1, root: id});
+				let head = match self.storage.get_down(id) {
+					Some(down) => down.head,
+					None => {
+						self.storage.set_down(id, Down {
+							head: K::null(),
+							tail: K::null(),
+							len: 0,
+							count: 0,
+						});
+						K::null()
+					}
+				};
+				// 节点可能是之前断开的子树的根，其Down::count不一定可靠，用设置layer时遍历到的节点数量修正
+				let count = self.insert_tree(head, Layer {layer: 2, root: id});
+				self.storage.down_mut(id).count = count;
+				self.storage.set_layer(id, Layer {layer: 1, root: id}); // 设置第二遍，表明为子树的根
+			},
+		};
+    }
+	
+    // 插入到树上， 就是先序设置id及其后的兄弟节点、以及它们的递归子节点的layer，返回设置的节点数量
+	// 使用显式的栈而不是递归，避免很深的树导致栈溢出
+	// 安全：调用该方法，确保!layer.is_null()
+    fn insert_tree(&mut self, id: K, layer: Layer<K>) -> usize {
+		let mut count = 0;
+		let mut arr = Vec::new();
+		if !id.is_null() {
+			arr.push((id, layer));
+		}
+        while let Some((id, layer)) = arr.pop() {
+			count += 1;
+			self.storage.set_layer(id, layer.clone());
+			// 先压入兄弟节点，再压入子节点，保证先处理完子树再处理兄弟节点
+			if let Some(up) = self.storage.get_up(id) {
+				if !up.next.is_null() {
+					arr.push((up.next, layer.clone()));
+				}
+			}
+			let head = self.storage.get_down(id).map_or(K::null(), |down|{down.head});
+			if !head.is_null() {
+				arr.push((head, Layer {layer: layer.layer + 1, root: layer.root}));
+			}
+        }
+		count
+    }
+    // 从树上移除， 就是先序删除id及其后的兄弟节点、以及它们的递归子节点的layer，同样使用显式的栈
+    fn remove_tree(&mut self, id: K) {
+		let mut arr = Vec::new();
+		if !id.is_null() {
+			arr.push(id);
+		}
+        while let Some(id) = arr.pop() {
+            self.storage.remove_layer(id); // 删除layer
+
+			let next = self.storage.up(id).next;
+			if !next.is_null() {
+				arr.push(next);
+			}
+			if let Some(down) = self.storage.get_down(id) {
+				// 如果存在子节点，则继续删除子节点的layer
+				if !down.head.is_null() {
+					arr.push(down.head);
+				}
+			}
+		}
+    }
+    // // 递归销毁
+    // fn recursive_destroy(&mut self, parent: K, mut id: K) {
+	// 	self.storage.delete_children(parent);
+    //     while !id.is_null() {
+	// 		if let Some(down) = self.storage.get_children(id) {
+	// 			self.recursive_destroy(id, down.head);
+	// 		};
+    //         id = self.storage.parent(id).next;
+    //     }
+    // }
+
+    // 递归向上，修改节点的count
+    fn modify_count(&mut self, mut id: K, count: isize) {
+		if self.counts_frozen {
+			return;
+		}
+        while !id.is_null() {
+			let down = self.storage.down_mut(id);
+			down.count = (down.count as isize + count) as usize;
+			// 结构变化的通知由调用者通过notify发出（见on_structure_change），这里只修改count
+			if let Some(up) = self.storage.get_up_mut(id) {
+				id = up.parent;
+			} else {
+				break;
+			}
+        }
+    }
+    // 移除节点
+    fn remove_node(&mut self, id: K, parent: K, count: usize, prev: K, next: K) {
+        // 修改prev和next的节点
+        if !prev.is_null() {
+            let node = self.storage.up_mut(prev);
+			node.next = next;
+        }
+        if !next.is_null() {
+            let node = self.storage.up_mut(next);
+			node.prev = prev;
+        }
+        
+		// 修改parent的children, count
+		let frozen = self.counts_frozen;
+		let p_down = self.storage.down_mut(parent) ;
+		if prev.is_null() {
+			p_down.head = next;
+		}
+		if next.is_null() {
+			p_down.tail = prev;
+		}
+		p_down.len -= 1;
+		if frozen {
+			// 冻结期间子树的count可能已经过时（大于父节点记录的值），不能下溢，unfreeze_counts时会重新计算
+			p_down.count = p_down.count.saturating_sub(count);
+		} else {
+			p_down.count -= count;
+		}
+
+		let p_p = self.storage.get_up(parent).map_or(K::null(), |up|{up.parent});
+            
+
+        // 递归向上修改count
+		self.modify_count(p_p, -(count as isize));
+
+		// 设置节点的层为None
+		self.storage.remove_layer(id);
+
+		// 设置up信息为null
+		self.storage.remove_up(id);
+    }
+}
+
+// pub struct ChildrenMutIterator<'a, K: Null, S: Storage<K>> {
+//     inner: &'a mut S,
+//     head: K,
+// }
+// impl<'a, K: Null, S: Storage<K>> Iterator for ChildrenMutIterator<'a, K, S> {
+//     type Item = K;
+//     fn next(&mut self) -> Option<Self::Item> {
+// 		if self.head.is_null() {
+// 			return None;
+// 		}
+// 		let head = self.head;
+
+//         let inner = unsafe { &mut *(self.inner as *mut S) };
+//         let n = unsafe { inner.get_unchecked_mut(head) };
+//         let next = n.next;
+//         let r = Some((head, n));
+//         self.head = next;
+//         r
+//     }
+// }
+pub struct ChildrenIterator<'a, K: Null + Copy + Clone, S: Storage<K>>{
+    inner: &'a S,
+    head: K,
+    tail: K, // 为null时表示尾节点未知，反向迭代时再查找
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> ChildrenIterator<'a, K, S> {
+	pub fn new(s: &'a S, head: K) -> Self {
+		ChildrenIterator {
+			inner: s,
+			head,
+			tail: K::null(),
+		}
+	}
+
+	/// 迭代parent的所有子节点，head和tail都从parent的Down中取得
+	pub fn from_parent(s: &'a S, parent: K) -> Self {
+		let (head, tail) = s.get_down(parent).map_or((K::null(), K::null()), |down|{(down.head, down.tail)});
+		ChildrenIterator {
+			inner: s,
+			head,
+			tail,
+		}
+	}
+}
+
+impl<'a, K: Null + Eq + Copy + Clone, S: Storage<K>> Iterator for ChildrenIterator<'a, K, S> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+		if self.head.is_null() {
+			return None;
+		}
+		let r = self.head;
+		if self.head == self.tail {
+			// 与反向迭代相遇
+			self.head = K::null();
+			self.tail = K::null();
+			return Some(r);
+		}
+        match self.inner.get_up(self.head) {
+			Some(up) => self.head = up.next,
+			None => self.head = K::null(),
+		};
+        Some(r)
+    }
+}
+
+impl<'a, K: Null + Eq + Copy + Clone, S: Storage<K>> DoubleEndedIterator for ChildrenIterator<'a, K, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+		if self.head.is_null() {
+			return None;
+		}
+		if self.tail.is_null() {
+			// 只知道头节点，向后找到尾节点
+			let mut tail = self.head;
+			while let Some(up) = self.inner.get_up(tail) {
+				if up.next.is_null() {
+					break;
+				}
+				tail = up.next;
+			}
+			self.tail = tail;
+		}
+		let r = self.tail;
+		if self.head == self.tail {
+			self.head = K::null();
+			self.tail = K::null();
+			return Some(r);
+		}
+		self.tail = self.inner.up(self.tail).prev;
+        Some(r)
+    }
+}
+
+/// 迭代父节点的所有子节点，数量取自父节点的Down::len，实现了ExactSizeIterator
+pub struct ExactChildrenIterator<'a, K: Null + Copy + Clone, S: Storage<K>>{
+    inner: ChildrenIterator<'a, K, S>,
+    len: usize, // 剩余的节点数量
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> ExactChildrenIterator<'a, K, S> {
+	pub fn from_parent(s: &'a S, parent: K) -> Self {
+		ExactChildrenIterator {
+			inner: ChildrenIterator::from_parent(s, parent),
+			len: s.get_down(parent).map_or(0, |down|{down.len}),
+		}
+	}
+}
+
+impl<'a, K: Null + Eq + Copy + Clone, S: Storage<K>> Iterator for ExactChildrenIterator<'a, K, S> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+		let r = self.inner.next()?;
+		self.len = self.len.saturating_sub(1);
+		Some(r)
+    }
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.len, Some(self.len))
+	}
+}
+
+impl<'a, K: Null + Eq + Copy + Clone, S: Storage<K>> ExactSizeIterator for ExactChildrenIterator<'a, K, S> {}
+
+impl<'a, K: Null + Eq + Copy + Clone, S: Storage<K>> DoubleEndedIterator for ExactChildrenIterator<'a, K, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+		let r = self.inner.next_back()?;
+		self.len = self.len.saturating_sub(1);
+		Some(r)
+    }
+}
+
+pub struct AncestorIterator<'a, K: Null + Copy + Clone, S: Storage<K>>{
+    inner: &'a S,
+    node: K,
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> AncestorIterator<'a, K, S> {
+	pub fn new(s: &'a S, node: K) -> Self {
+		AncestorIterator {
+			inner: s,
+			node
+		}
+	}
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> Iterator for AncestorIterator<'a, K, S> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+		if self.node.is_null() {
+			return None;
+		}
+		self.node = match self.inner.get_up(self.node) {
+			Some(up) => up.parent,
+			None => K::null(),
+		};
+		if self.node.is_null() {
+			None
+		} else {
+			Some(self.node)
+		}
+    }
+}
+
+pub struct SiblingIterator<'a, K: Null + Copy + Clone, S: Storage<K>>{
+    inner: ChildrenIterator<'a, K, S>,
+    exclude: K,
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> SiblingIterator<'a, K, S> {
+	/// 从head开始迭代兄弟节点，跳过exclude
+	pub fn new(s: &'a S, head: K, exclude: K) -> Self {
+		SiblingIterator {
+			inner: ChildrenIterator::new(s, head),
+			exclude
+		}
+	}
+}
+
+impl<'a, K: Null + Eq + Copy + Clone, S: Storage<K>> Iterator for SiblingIterator<'a, K, S> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+		let r = self.inner.next()?;
+		if r == self.exclude {
+			return self.inner.next();
+		}
+		Some(r)
+    }
+}
+
+pub struct LeafIterator<'a, K: Null, S: Storage<K>> {
+    inner: &'a S,
+    root: K,
+    arr: Vec<K>,
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> LeafIterator<'a, K, S> {
+	pub fn new(s: &'a S, root: K) -> Self {
+		let mut arr = Vec::new();
+		if !root.is_null() {
+			arr.push(root);
+		}
+		LeafIterator {
+			inner: s,
+			root,
+			arr,
+		}
+	}
+}
+
+impl<'a, K: Null + Eq + Copy + Clone, S: Storage<K>> Iterator for LeafIterator<'a, K, S> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+		while let Some(id) = self.arr.pop() {
+			// 子树的根不迭代其兄弟节点
+			if id != self.root {
+				if let Some(up) = self.inner.get_up(id) {
+					if !up.next.is_null() {
+						self.arr.push(up.next);
+					}
+				}
+			}
+
+			match self.inner.get_down(id) {
+				Some(down) if !down.head.is_null() => self.arr.push(down.head),
+				_ => return Some(id),
+			}
+		}
+		None
+    }
+}
+
+pub struct DepthLimitedIterator<'a, K: Null, S: Storage<K>> {
+    inner: &'a S,
+    arr: Vec<(K, usize)>, // (节点, 相对root的深度)
+    max_depth: usize,
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> DepthLimitedIterator<'a, K, S> {
+	pub fn new(s: &'a S, root: K, max_depth: usize) -> Self {
+		let mut arr = Vec::new();
+		if !root.is_null() {
+			arr.push((root, 0));
+		}
+		DepthLimitedIterator {
+			inner: s,
+			arr,
+			max_depth,
+		}
+	}
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> DepthLimitedIterator<'a, K, S> {
+	/// 返回下一个节点及其相对root的深度
+	pub fn next_with_depth(&mut self) -> Option<(usize, K)> {
+		let (id, depth) = self.arr.pop()?;
+		// 子树的根不迭代其兄弟节点
+		if depth > 0 {
+			let next = self.inner.up(id).next;
+			if !next.is_null() {
+				self.arr.push((next, depth));
+			}
+		}
+		if depth < self.max_depth {
+			if let Some(down) = self.inner.get_down(id) {
+				if !down.head.is_null() {
+					self.arr.push((down.head, depth + 1));
+				}
+			}
+		}
+		Some((depth, id))
+	}
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> Iterator for DepthLimitedIterator<'a, K, S> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+		self.next_with_depth().map(|(_, id)|{id})
+    }
+}
+
+/// 先序迭代子树，同时返回每个节点相对子树根的深度
+pub struct DepthIterator<'a, K: Null, S: Storage<K>> {
+    inner: DepthLimitedIterator<'a, K, S>,
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> DepthIterator<'a, K, S> {
+	pub fn new(s: &'a S, root: K) -> Self {
+		DepthIterator {
+			inner: DepthLimitedIterator::new(s, root, usize::MAX),
+		}
+	}
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> Iterator for DepthIterator<'a, K, S> {
+    type Item = (usize, K);
+
+    fn next(&mut self) -> Option<Self::Item> {
+		self.inner.next_with_depth()
+    }
+}
+
+pub struct PostOrderIterator<'a, K: Null, S: Storage<K>> {
+    inner: &'a S,
+    arr: Vec<(K, bool)>, // (节点, 子节点是否已经入栈)
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> PostOrderIterator<'a, K, S> {
+	pub fn new(s: &'a S, root: K) -> Self {
+		let mut arr = Vec::new();
+		if !root.is_null() {
+			arr.push((root, false));
+		}
+		PostOrderIterator {
+			inner: s,
+			arr,
+		}
+	}
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> Iterator for PostOrderIterator<'a, K, S> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let (id, visited) = self.arr.pop()?;
+			if visited {
+				return Some(id);
+			}
+			self.arr.push((id, true));
+			// 子节点逆序入栈，保证按顺序出栈
+			let mut child = self.inner.get_down(id).map_or(K::null(), |down|{down.tail});
+			while !child.is_null() {
+				self.arr.push((child, false));
+				child = self.inner.up(child).prev;
+			}
+		}
+    }
+}
+
+pub struct BreadthFirstIterator<'a, K: Null, S: Storage<K>> {
+    inner: &'a S,
+    queue: VecDeque<K>,
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> BreadthFirstIterator<'a, K, S> {
+	pub fn new(s: &'a S, root: K) -> Self {
+		let mut queue = VecDeque::new();
+		if !root.is_null() {
+			queue.push_back(root);
+		}
+		BreadthFirstIterator {
+			inner: s,
+			queue,
+		}
+	}
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> Iterator for BreadthFirstIterator<'a, K, S> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+		let id = self.queue.pop_front()?;
+		let mut child = self.inner.get_down(id).map_or(K::null(), |down|{down.head});
+		while !child.is_null() {
+			self.queue.push_back(child);
+			child = self.inner.up(child).next;
+		}
+		Some(id)
+    }
+}
+
+pub struct RecursiveIterator<'a, K: Null, S: Storage<K>> {
+    inner: &'a S,
+    arr: Stack<'a, K>,
+    skip_next: bool, // 第一个节点是子树的根，不迭代它的兄弟节点
+}
+
+// 迭代使用的栈，可以自己持有，也可以借用调用者的Vec，以便在多次迭代间复用内存
+enum Stack<'a, K> {
+	Owned(Vec<K>),
+	Borrowed(&'a mut Vec<K>),
+}
+
+impl<'a, K> Deref for Stack<'a, K> {
+	type Target = Vec<K>;
+
+	fn deref(&self) -> &Self::Target {
+		match self {
+			Stack::Owned(v) => v,
+			Stack::Borrowed(v) => v,
+		}
+	}
+}
+
+impl<'a, K> DerefMut for Stack<'a, K> {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		match self {
+			Stack::Owned(v) => v,
+			Stack::Borrowed(v) => v,
+		}
+	}
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> RecursiveIterator<'a, K, S> {
+	/// len为0表示空迭代器，否则从head开始迭代
+	pub fn new(s: &'a S, head: K, len: usize) -> Self {
+		let mut arr = Vec::with_capacity(32);
+		if len > 0 {
+			arr.push(head);
+		}
+		RecursiveIterator {
+			inner: s,
+			arr: Stack::Owned(arr),
+			skip_next: false,
+		}
+	}
+
+	/// 从node开始迭代node及其递归子节点，不迭代node的兄弟节点
+	pub fn from_node(s: &'a S, node: K) -> Self {
+		let mut arr = Vec::with_capacity(32);
+		if !node.is_null() {
+			arr.push(node);
+		}
+		RecursiveIterator {
+			inner: s,
+			arr: Stack::Owned(arr),
+			skip_next: true,
+		}
+	}
+
+	/// 与from_node相同，但使用buf作为迭代的栈，构造时会清空buf
+	pub fn from_node_in(s: &'a S, node: K, buf: &'a mut Vec<K>) -> Self {
+		buf.clear();
+		if !node.is_null() {
+			buf.push(node);
+		}
+		RecursiveIterator {
+			inner: s,
+			arr: Stack::Borrowed(buf),
+			skip_next: true,
+		}
+	}
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> Iterator for RecursiveIterator<'a, K, S> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let head = self.arr.pop()?;
+		if self.skip_next {
+			self.skip_next = false;
+		} else if let Some(up) = self.inner.get_up(head) {
+			if !up.next.is_null() {
+				self.arr.push(up.next);
+			}
+		}
+
+		if let Some(down) = self.inner.get_down(head) {
+			if !down.head.is_null(){
+				self.arr.push(down.head);
+			}
+		};
+
+        Some(head)
+    }
+}
+/// 按根节点列表的顺序迭代所有根节点
+pub struct RootsIterator<'a, K: Null, S: Storage<K> + ?Sized> {
+	inner: &'a S,
+	cur: K,
+}
+
+impl<'a, K: Null, S: Storage<K> + ?Sized> RootsIterator<'a, K, S> {
+	pub fn new(s: &'a S) -> Self {
+		RootsIterator {
+			inner: s,
+			cur: s.first_root(),
+		}
+	}
+}
+
+impl<'a, K: Null + Copy, S: Storage<K> + ?Sized> Iterator for RootsIterator<'a, K, S> {
+	type Item = K;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.cur.is_null() {
+			return None;
+		}
+		let r = self.cur;
+		self.cur = self.inner.next_root(r);
+		Some(r)
+	}
+}
+
+/// 按根节点列表的顺序，依次先序迭代每棵树上的所有节点
+pub struct ForestIterator<'a, K: Null, S: Storage<K>> {
+    inner: &'a S,
+    roots: RootsIterator<'a, K, S>,
+    cur: RecursiveIterator<'a, K, S>,
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> ForestIterator<'a, K, S> {
+	pub fn new(s: &'a S) -> Self {
+		ForestIterator {
+			inner: s,
+			roots: RootsIterator::new(s),
+			cur: RecursiveIterator::new(s, K::null(), 0),
+		}
+	}
+}
+
+impl<'a, K: Null + Copy + Clone, S: Storage<K>> Iterator for ForestIterator<'a, K, S> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some(r) = self.cur.next() {
+				return Some(r);
+			}
+			let root = self.roots.next()?;
+			self.cur = RecursiveIterator::from_node(self.inner, root);
+		}
+    }
+}
+
+/// 迭代森林中所有在树上的节点：按根节点的插入顺序，对每棵树先序迭代
+impl<'a, K: Null + Eq + Copy + Clone, S: Storage<K>, L> IntoIterator for &'a Tree<K, S, L> {
+	type Item = K;
+	type IntoIter = ForestIterator<'a, K, S>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		ForestIterator::new(&self.storage)
+	}
+}
+
+// 关闭std特性时，cargo build --no-default-features会编译本模块，检查no_std + alloc下的核心接口可用（不需要运行）
+#[cfg(not(feature = "std"))]
+#[allow(dead_code)]
+mod no_std_compile_test {
+	use alloc::vec::Vec;
+	use pi_null::Null;
+	use crate::{Tree, TreeBuilder, VecTree, InsertType, Target};
+
+	fn build() -> Vec<usize> {
+		let mut tree: Tree<usize, VecTree> = Tree::new(VecTree::default());
+		tree.insert_child(0, usize::null(), usize::MAX);
+		tree.insert_children(0, &[1, 2, 3]);
+		tree.insert_brother(4, 2, InsertType::Back);
+		tree.insert(5, Target::ChildOf { parent: 1, order: 0 });
+		TreeBuilder::node(6).child(7).child_with(8, |b| b.child(9)).commit_to(&mut tree, 3);
+		tree.move_subtree(6, 1, usize::MAX);
+		tree.remove(2);
+		tree.remove_recursive(4);
+		tree.sort_children_by(0, |a, b| b.cmp(&a));
+		let _ = tree.validate();
+		let _ = tree.format_subtree(0, |k| alloc::format!("{}", k));
+		tree.recursive_iter(0).chain(tree.breadth_first_iter(0)).collect()
+	}
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+	use super::*;
+	use pi_slotmap::SlotMap;
+	use crate::slot_map_tree::{SlotMapTree, TreeKey};
+
+	// 测试用：空的树，以及为节点分配key的SlotMap
+	fn fixture() -> (Tree<TreeKey, SlotMapTree>, SlotMap<TreeKey, ()>) {
+		(Tree::new(SlotMapTree::default()), SlotMap::default())
+	}
+
+	// 测试用：直接修改节点的Layer，用于构造被破坏的树
+	fn edit_layer<S: StorageMut<TreeKey>>(tree: &mut Tree<TreeKey, S>, k: TreeKey, f: impl FnOnce(&mut Layer<TreeKey>)) {
+		let mut layer = tree.storage.layer(k).clone();
+		f(&mut layer);
+		tree.storage.set_layer(k, layer);
+	}
+
+	// 测试用：各存储共用的基本插入、删除测试，keys为9个互不相同的key
+	pub(crate) fn check_storage<K: Null + Eq + Copy + Debug, S: StorageMut<K>>(mut tree: Tree<K, S>, keys: [K; 9]) {
+		let [p1, c1, c2, c3, c4, c5, r1, r2, r3] = keys;
+		tree.insert_child(p1, K::null(), usize::MAX);
+		tree.insert_child(c4, p1, usize::MAX);
+		tree.insert_child(c3, p1, usize::MAX);
+		tree.insert_brother(c1, c4, InsertType::Front);
+		tree.insert_brother(c2, c4, InsertType::Front);
+		tree.insert_brother(c3, c4, InsertType::Front);
+		tree.insert_child(c5, c4, usize::MAX);
+
+		assert_eq!(tree.collect_children(p1), vec![c1, c2, c3, c4]);
+		assert_eq!(tree.get_down(p1).unwrap().count(), 5);
+		assert_eq!(tree.depth(c5), Some(3));
+
+		tree.remove(c4);
+		assert_eq!(tree.collect_children(p1), vec![c1, c2, c3]);
+		assert_eq!(tree.get_down(p1).unwrap().count(), 3);
+		assert!(tree.get_up(c4).is_none());
+		assert!(tree.get_layer(c4).is_none());
+		assert_eq!(tree.depth(c5), None);
+		assert_eq!(tree.validate_subtree(p1), Ok(()));
+
+		// 删除中间的根节点后，其余根节点的顺序不变
+		for r in [r1, r2, r3] {
+			tree.insert_child(r, K::null(), usize::MAX);
+		}
+		tree.remove(r2);
+		tree.remove(p1);
+		tree.insert_child(r2, K::null(), usize::MAX);
+		tree.remove(r3);
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![r1, r2]);
+	}
+
+	#[cfg(feature = "rayon")]
+
+	#[test]
+	fn test_par_for_each_child_subtree() {
+		use std::collections::HashSet;
+		use std::sync::Mutex;
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+
+		let mut all = HashSet::new();
+		for _ in 0..4 {
+			let c = slotmap.insert(());
+			tree.insert_child(c, root, usize::MAX);
+			all.insert(c);
+			for _ in 0..3 {
+				let cc = slotmap.insert(());
+				tree.insert_child(cc, c, usize::MAX);
+				all.insert(cc);
+				let ccc = slotmap.insert(());
+				tree.insert_child(ccc, cc, usize::MAX);
+				all.insert(ccc);
+			}
+		}
+
+		let visited = Mutex::new(HashSet::new());
+		tree.par_for_each_child_subtree(root, |k| {
+			// 每个节点只能被访问一次
+			assert!(visited.lock().unwrap().insert(k));
+		});
+		assert_eq!(visited.into_inner().unwrap(), all);
+	}
+
+	#[test]
+	fn test_recursive_iter_deep() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+
+		let mut keys = vec![root];
+		let mut parent = root;
+		for _ in 0..1000 {
+			let c = slotmap.insert(());
+			tree.insert_child(c, parent, usize::MAX);
+			keys.push(c);
+			parent = c;
+		}
+
+		let r: Vec<TreeKey> = tree.recursive_iter(root).collect();
+		assert_eq!(r, keys);
+	}
+
+	#[test]
+	fn test_ancestors() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let c1 = slotmap.insert(());
+		let c2 = slotmap.insert(());
+		let c3 = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(c1, root, usize::MAX);
+		tree.insert_child(c2, c1, usize::MAX);
+		tree.insert_child(c3, c2, usize::MAX);
+
+		assert_eq!(tree.ancestors(c3).collect::<Vec<_>>(), vec![c2, c1, root]);
+		assert_eq!(tree.ancestors(c1).collect::<Vec<_>>(), vec![root]);
+		assert_eq!(tree.ancestors(root).count(), 0);
+	}
+
+	#[test]
+	fn test_siblings() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let children: Vec<TreeKey> = (0..5).map(|_| slotmap.insert(())).collect();
+		for c in children.iter() {
+			tree.insert_child(*c, root, usize::MAX);
+		}
+
+		assert_eq!(tree.siblings(children[2]).collect::<Vec<_>>(), vec![children[0], children[1], children[3], children[4]]);
+		assert_eq!(tree.siblings_with_self(children[2]).collect::<Vec<_>>(), children);
+		assert_eq!(tree.siblings(root).count(), 0);
+	}
+
+	#[test]
+	fn test_depth() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let c1 = slotmap.insert(());
+		let c2 = slotmap.insert(());
+		let c3 = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(c1, root, usize::MAX);
+		tree.insert_child(c2, c1, usize::MAX);
+
+		assert_eq!(tree.depth(root), Some(1));
+		assert_eq!(tree.depth(c2), Some(3));
+		assert_eq!(tree.root_of(c2), Some(root));
+		assert_eq!(tree.depth(c3), None);
+		assert_eq!(tree.root_of(c3), None);
+
+		tree.remove(c1);
+		assert_eq!(tree.depth(c1), None);
+		assert_eq!(tree.root_of(c2), None);
+	}
+
+	#[test]
+	fn test_is_ancestor() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let c1 = slotmap.insert(());
+		let c2 = slotmap.insert(());
+		let c3 = slotmap.insert(());
+		let root2 = slotmap.insert(());
+		let d1 = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(c1, root, usize::MAX);
+		tree.insert_child(c2, c1, usize::MAX);
+		tree.insert_child(c3, c2, usize::MAX);
+		tree.insert_child(root2, TreeKey::null(), usize::MAX);
+		tree.insert_child(d1, root2, usize::MAX);
+
+		assert!(!tree.is_ancestor(c2, c2));
+		assert!(tree.is_ancestor(c2, c3));
+		assert!(tree.is_ancestor(root, c3));
+		assert!(!tree.is_ancestor(c3, root));
+		assert!(!tree.is_ancestor(root, d1));
+		assert!(!tree.is_ancestor(root2, c3));
+	}
+
+	#[test]
+	fn test_lowest_common_ancestor() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let c1 = slotmap.insert(());
+		let c2 = slotmap.insert(());
+		let c3 = slotmap.insert(());
+		let c4 = slotmap.insert(());
+		let root2 = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(c1, root, usize::MAX);
+		tree.insert_child(c2, c1, usize::MAX);
+		tree.insert_child(c3, c2, usize::MAX);
+		tree.insert_child(c4, root, usize::MAX);
+		tree.insert_child(root2, TreeKey::null(), usize::MAX);
+
+		assert_eq!(tree.lowest_common_ancestor(c3, c4), Some(root));
+		assert_eq!(tree.lowest_common_ancestor(c4, c3), Some(root));
+		assert_eq!(tree.lowest_common_ancestor(c1, c3), Some(c1));
+		assert_eq!(tree.lowest_common_ancestor(c3, c1), Some(c1));
+		assert_eq!(tree.lowest_common_ancestor(c2, c2), Some(c2));
+		assert_eq!(tree.lowest_common_ancestor(c2, root2), None);
+	}
+
+	#[test]
+	fn test_move_subtree() {
+		let (mut tree, mut slotmap) = fixture();
+		let r1 = slotmap.insert(());
+		let r2 = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let x = slotmap.insert(());
+		let y = slotmap.insert(());
+		tree.insert_child(r1, TreeKey::null(), usize::MAX);
+		tree.insert_child(r2, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, r1, usize::MAX);
+		tree.insert_child(b, a, usize::MAX);
+		tree.insert_child(c, b, usize::MAX);
+		tree.insert_child(x, r2, usize::MAX);
+		tree.insert_child(y, x, usize::MAX);
+
+		// 移动到另一棵树更深的位置
+		tree.move_subtree(a, x, 0);
+		assert_eq!(tree.get_down(r1).unwrap().len(), 0);
+		assert_eq!(tree.get_down(r1).unwrap().count(), 0);
+		assert_eq!(tree.get_down(x).unwrap().len(), 2);
+		assert_eq!(tree.get_down(x).unwrap().count(), 4);
+		assert_eq!(tree.get_down(r2).unwrap().count(), 5);
+		assert_eq!(tree.iter(tree.get_down(x).unwrap().head()).collect::<Vec<_>>(), vec![a, y]);
+		for (k, layer) in [(a, 3), (b, 4), (c, 5)] {
+			assert_eq!(tree.depth(k), Some(layer));
+			assert_eq!(tree.root_of(k), Some(r2));
+		}
+
+		// 移回原来的树
+		tree.move_subtree(a, r1, usize::MAX);
+		assert_eq!(tree.get_down(r1).unwrap().count(), 3);
+		assert_eq!(tree.get_down(x).unwrap().count(), 1);
+		assert_eq!(tree.get_down(r2).unwrap().count(), 2);
+		for (k, layer) in [(a, 2), (b, 3), (c, 4)] {
+			assert_eq!(tree.depth(k), Some(layer));
+			assert_eq!(tree.root_of(k), Some(r1));
+		}
+
+		// 移动到自己的子树下失败，树不做任何修改
+		tree.move_subtree(a, c, 0);
+		tree.move_subtree(a, a, 0);
+		assert_eq!(tree.parent(a), Some(r1));
+		assert_eq!(tree.collect_children(r1), vec![a]);
+		assert_eq!(tree.get_down(r1).unwrap().count(), 3);
+		assert!(tree.validate_subtree(r1).is_ok());
+	}
+
+	#[test]
+	fn test_swap() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let c: Vec<TreeKey> = (0..4).map(|_| slotmap.insert(())).collect();
+		for k in c.iter() {
+			tree.insert_child(*k, root, usize::MAX);
+		}
+		let children = |tree: &Tree<TreeKey, SlotMapTree>, parent: TreeKey| tree.iter(tree.get_down(parent).unwrap().head()).collect::<Vec<_>>();
+
+		// 相邻节点
+		tree.swap(c[0], c[1]);
+		assert_eq!(children(&tree, root), vec![c[1], c[0], c[2], c[3]]);
+		tree.swap(c[0], c[1]);
+		assert_eq!(children(&tree, root), vec![c[0], c[1], c[2], c[3]]);
+		// 不相邻节点，包含头尾
+		tree.swap(c[3], c[0]);
+		assert_eq!(children(&tree, root), vec![c[3], c[1], c[2], c[0]]);
+		assert_eq!(tree.get_down(root).unwrap().tail(), c[0]);
+		assert_eq!(tree.get_up(c[0]).unwrap().prev(), c[2]);
+
+		// 不同父节点、不同层
+		let d = slotmap.insert(());
+		let e = slotmap.insert(());
+		tree.insert_child(d, c[1], usize::MAX);
+		tree.insert_child(e, c[2], usize::MAX);
+		tree.swap(d, c[3]);
+		assert_eq!(children(&tree, root), vec![d, c[1], c[2], c[0]]);
+		assert_eq!(children(&tree, c[1]), vec![c[3]]);
+		assert_eq!(tree.depth(d), Some(2));
+		assert_eq!(tree.depth(c[3]), Some(3));
+		assert_eq!(tree.get_down(root).unwrap().count(), 6);
+
+		// 子树跟随节点移动，count正确
+		tree.swap(c[2], c[3]);
+		assert_eq!(children(&tree, c[1]), vec![c[2]]);
+		assert_eq!(tree.depth(e), Some(4));
+		assert_eq!(tree.get_down(c[1]).unwrap().count(), 2);
+		assert_eq!(tree.get_down(root).unwrap().count(), 6);
+	}
+
+	#[test]
+	fn test_reverse_children() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let mut c: Vec<TreeKey> = (0..5).map(|_| slotmap.insert(())).collect();
+		for k in c.iter() {
+			tree.insert_child(*k, root, usize::MAX);
+		}
+
+		tree.reverse_children(root);
+		c.reverse();
+		assert_eq!(tree.iter(tree.get_down(root).unwrap().head()).collect::<Vec<_>>(), c);
+		assert_eq!(tree.get_down(root).unwrap().tail(), c[4]);
+		assert_eq!(tree.get_up(c[4]).unwrap().prev(), c[3]);
+		assert!(tree.get_up(c[0]).unwrap().prev().is_null());
+	}
+
+	#[test]
+	fn test_sort_children_by() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let mut c: Vec<TreeKey> = (0..5).map(|_| slotmap.insert(())).collect();
+		for k in c.iter() {
+			tree.insert_child(*k, root, usize::MAX);
+		}
+
+		// 外部数据决定顺序
+		let priority: std::collections::HashMap<TreeKey, usize> = c.iter().enumerate().map(|(i, k)| (*k, i)).collect();
+		tree.sort_children_by(root, |a, b| priority[&b].cmp(&priority[&a]));
+		c.reverse();
+		assert_eq!(tree.iter(tree.get_down(root).unwrap().head()).collect::<Vec<_>>(), c);
+		assert_eq!(tree.get_down(root).unwrap().tail(), c[4]);
+		assert_eq!(tree.get_down(root).unwrap().len(), 5);
+		assert_eq!(tree.get_up(c[4]).unwrap().prev(), c[3]);
+	}
+
+	#[test]
+	fn test_detach() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, a, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+		tree.insert_child(d, c, usize::MAX);
+
+		tree.detach(a);
+		assert_eq!(tree.get_down(root).unwrap().len(), 0);
+		assert_eq!(tree.get_down(root).unwrap().count(), 0);
+		assert!(tree.get_up(a).is_none());
+		assert_eq!(tree.get_down(a).unwrap().count(), 3);
+		assert_eq!(tree.depth(a), None);
+		// 子节点保留断开前的layer
+		for (k, layer) in [(b, 3), (c, 3), (d, 4)] {
+			assert_eq!(tree.depth(k), Some(layer));
+		}
+		assert_eq!(tree.get_up(d).unwrap().parent(), c);
+
+		// 重新挂回，整个子树恢复
+		tree.insert_child(a, root, usize::MAX);
+		assert_eq!(tree.get_down(root).unwrap().count(), 4);
+		for (k, layer) in [(a, 2), (b, 3), (c, 3), (d, 4)] {
+			assert_eq!(tree.depth(k), Some(layer));
+		}
+
+		// 断开根节点，remove清除保留的layer
+		tree.detach(root);
+		assert_eq!(tree.roots().count(), 0);
+		assert_eq!(tree.depth(root), None);
+		assert_eq!(tree.depth(d), Some(4));
+		tree.remove(root);
+		for k in [root, a, b, c, d] {
+			assert_eq!(tree.depth(k), None);
+		}
+	}
+
+	#[test]
+	fn test_clear() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let mut keys = vec![root];
+		for i in 0..11 {
+			let k = slotmap.insert(());
+			tree.insert_child(k, keys[i / 3], usize::MAX);
+			keys.push(k);
+		}
+
+		tree.clear();
+		for k in keys {
+			assert!(tree.get_up(k).is_none());
+			assert!(tree.get_down(k).is_none());
+			assert!(tree.get_layer(k).is_none());
+		}
+	}
+
+	#[test]
+	fn test_default_clear() {
+		// 不实现clear的存储，使用StorageMut::clear的默认实现
+		struct NoClear(SlotMapTree);
+		impl Storage<TreeKey> for NoClear {
+			fn get_up(&self, k: TreeKey) -> Option<&Up<TreeKey>> { self.0.get_up(k) }
+			fn up(&self, k: TreeKey) -> &Up<TreeKey> { self.0.up(k) }
+			fn get_layer(&self, k: TreeKey) -> Option<&Layer<TreeKey>> { self.0.get_layer(k) }
+			fn layer(&self, k: TreeKey) -> &Layer<TreeKey> { self.0.layer(k) }
+			fn get_down(&self, k: TreeKey) -> Option<&Down<TreeKey>> { self.0.get_down(k) }
+			fn down(&self, k: TreeKey) -> &Down<TreeKey> { self.0.down(k) }
+			fn first_root(&self) -> TreeKey { self.0.first_root() }
+			fn next_root(&self, k: TreeKey) -> TreeKey { self.0.next_root(k) }
+		}
+		impl StorageMut<TreeKey> for NoClear {
+			fn get_up_mut(&mut self, k: TreeKey) -> Option<&mut Up<TreeKey>> { self.0.get_up_mut(k) }
+			fn set_up(&mut self, k: TreeKey, parent: Up<TreeKey>) { self.0.set_up(k, parent) }
+			fn up_mut(&mut self, k: TreeKey) -> &mut Up<TreeKey> { self.0.up_mut(k) }
+			fn remove_up(&mut self, k: TreeKey) { self.0.remove_up(k) }
+			fn set_layer(&mut self, k: TreeKey, layer: Layer<TreeKey>) { self.0.set_layer(k, layer) }
+			fn remove_layer(&mut self, k: TreeKey) { self.0.remove_layer(k) }
+			fn get_down_mut(&mut self, k: TreeKey) -> Option<&mut Down<TreeKey>> { self.0.get_down_mut(k) }
+			fn set_down(&mut self, k: TreeKey, children: Down<TreeKey>) { self.0.set_down(k, children) }
+			fn down_mut(&mut self, k: TreeKey) -> &mut Down<TreeKey> { self.0.down_mut(k) }
+			fn remove_down(&mut self, k: TreeKey) { self.0.remove_down(k) }
+			fn set_root(&mut self, k: TreeKey) { self.0.set_root(k) }
+			fn remove_root(&mut self, k: TreeKey) { self.0.remove_root(k) }
+		}
+
+		let mut tree: Tree<TreeKey, NoClear> = Tree::new(NoClear(SlotMapTree::default()));
+		let mut slotmap = SlotMap::default();
+		let mut keys = Vec::new();
+		for _ in 0..2 {
+			let root = slotmap.insert(());
+			tree.insert_child(root, TreeKey::null(), usize::MAX);
+			keys.push(root);
+			for i in 0..5 {
+				let k = slotmap.insert(());
+				tree.insert_child(k, keys[keys.len() - 1 - i / 2], usize::MAX);
+				keys.push(k);
+			}
+		}
+
+		tree.clear();
+		assert_eq!(tree.roots().count(), 0);
+		for k in keys {
+			assert!(tree.get_up(k).is_none());
+			assert!(tree.get_down(k).is_none());
+			assert!(tree.get_layer(k).is_none());
+		}
+	}
+
+	#[test]
+	fn test_leaves() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		let e = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+		tree.insert_child(d, a, usize::MAX);
+		tree.insert_child(e, b, usize::MAX);
+		// e被移除后，b仍有down，但不再有子节点
+		tree.remove(e);
+
+		assert_eq!(tree.leaves(root).collect::<Vec<_>>(), vec![c, d, b]);
+		// 子树的根不会迭代到兄弟节点
+		assert_eq!(tree.leaves(a).collect::<Vec<_>>(), vec![c, d]);
+		assert_eq!(tree.leaves(c).collect::<Vec<_>>(), vec![c]);
+	}
+
+	#[test]
+	fn test_recursive_iter_post() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		let e = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+		tree.insert_child(d, a, usize::MAX);
+		tree.insert_child(e, b, usize::MAX);
+
+		let r: Vec<TreeKey> = tree.recursive_iter_post(root).collect();
+		assert_eq!(r, vec![c, d, a, e, b, root]);
+		// 每个节点都在其父节点之前
+		for (i, k) in r.iter().enumerate() {
+			if let Some(up) = tree.get_up(*k) {
+				assert!(r.iter().position(|p| *p == up.parent()).unwrap() > i);
+			}
+		}
+		assert_eq!(tree.recursive_iter_post(a).collect::<Vec<_>>(), vec![c, d, a]);
+	}
+
+	#[test]
+	fn test_breadth_first_iter() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		let e = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+		tree.insert_child(d, c, usize::MAX);
+		tree.insert_child(e, b, usize::MAX);
+
+		let r: Vec<TreeKey> = tree.breadth_first_iter(root).collect();
+		assert_eq!(r, vec![root, a, b, c, e, d]);
+		let layers: Vec<crate::LayerIndex> = r.iter().map(|k| tree.get_layer(*k).unwrap().layer()).collect();
+		assert!(layers.windows(2).all(|w| w[0] <= w[1]));
+	}
+
+	#[test]
+	fn test_recursive_iter_depth() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+		tree.insert_child(d, c, usize::MAX);
+
+		assert_eq!(tree.recursive_iter_depth(root, 0).collect::<Vec<_>>(), vec![root]);
+		assert_eq!(tree.recursive_iter_depth(root, 1).collect::<Vec<_>>(), vec![root, a, b]);
+		assert_eq!(tree.recursive_iter_depth(root, 2).collect::<Vec<_>>(), vec![root, a, c, b]);
+		assert_eq!(tree.recursive_iter_depth(root, 10).collect::<Vec<_>>(), vec![root, a, c, d, b]);
+		assert_eq!(tree.recursive_iter_depth(a, 1).collect::<Vec<_>>(), vec![a, c]);
+	}
+
+	#[test]
+	fn test_child_count() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+		tree.insert_child(d, c, usize::MAX);
+
+		assert_eq!(tree.child_count(root), 2);
+		assert_eq!(tree.descendant_count(root), 4);
+		assert_eq!(tree.child_count(a), 1);
+		assert_eq!(tree.descendant_count(a), 2);
+		assert_eq!(tree.child_count(d), 0);
+		assert_eq!(tree.descendant_count(d), 0);
+		for k in [root, a, b, c, d] {
+			assert_eq!(tree.descendant_count(k), tree.recursive_iter_depth(k, usize::MAX).count() - 1);
+		}
+	}
+
+	#[test]
+	fn test_navigation() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(c, root, usize::MAX);
+
+		assert_eq!(tree.parent(root), None);
+		assert_eq!(tree.parent(b), Some(root));
+		assert_eq!(tree.first_child(root), Some(a));
+		assert_eq!(tree.last_child(root), Some(c));
+		assert_eq!(tree.first_child(a), None);
+		assert_eq!(tree.next_sibling(a), Some(b));
+		assert_eq!(tree.next_sibling(c), None);
+		assert_eq!(tree.prev_sibling(c), Some(b));
+		assert_eq!(tree.prev_sibling(a), None);
+	}
+
+	#[test]
+	fn test_serialize() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let mut keys = vec![root];
+		for i in 0..8 {
+			let k = slotmap.insert(());
+			tree.insert_child(k, keys[i / 2], usize::MAX);
+			keys.push(k);
+		}
+
+		let root1 = slotmap.insert(());
+		tree.insert_child(root1, TreeKey::null(), usize::MAX);
+
+		let json = serde_json::to_string(tree.get_storage()).unwrap();
+		let mut storage: SlotMapTree = serde_json::from_str(&json).unwrap();
+		for k in keys {
+			assert_eq!(format!("{:?}", tree.get_up(k)), format!("{:?}", storage.get_up(k)));
+			assert_eq!(format!("{:?}", tree.get_down(k)), format!("{:?}", storage.get_down(k)));
+			assert_eq!(format!("{:?}", tree.get_layer(k)), format!("{:?}", storage.get_layer(k)));
+		}
+		// 根节点列表按原顺序恢复，并且重建了链表
+		assert_eq!(RootsIterator::new(&storage).collect::<Vec<_>>(), vec![root, root1]);
+		storage.remove_root(root);
+		storage.set_root(root1);
+		assert_eq!(RootsIterator::new(&storage).collect::<Vec<_>>(), vec![root1]);
+	}
+
+	#[test]
+	fn test_validate() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+		assert_eq!(tree.validate_subtree(root), Ok(()));
+
+		tree.storage.down_mut(root).count = 5;
+		assert_eq!(tree.validate_subtree(root), Err(TreeError::CountMismatch { node: root, expected: 3, found: 5 }));
+		tree.storage.down_mut(root).count = 3;
+
+		tree.storage.down_mut(root).tail = a;
+		assert_eq!(tree.validate_subtree(root), Err(TreeError::TailMismatch { node: root, expected: b, found: a }));
+		tree.storage.down_mut(root).tail = b;
+
+		tree.storage.up_mut(b).prev = TreeKey::null();
+		assert_eq!(tree.validate_subtree(root), Err(TreeError::PrevMismatch { node: b, expected: a, found: TreeKey::null() }));
+		tree.storage.up_mut(b).prev = a;
+
+		edit_layer(&mut tree, c, |layer| layer.layer = 2);
+		assert_eq!(tree.validate_subtree(root), Err(TreeError::LayerMismatch { node: c, expected: 3, found: 2 }));
+		edit_layer(&mut tree, c, |layer| layer.layer = 3);
+
+		tree.storage.down_mut(a).len = 2;
+		assert_eq!(tree.validate_subtree(root), Err(TreeError::LenMismatch { node: a, expected: 1, found: 2 }));
+		tree.storage.down_mut(a).len = 1;
+		assert_eq!(tree.validate_subtree(root), Ok(()));
+
+		// validate校验所有根节点的树
+		let root1 = slotmap.insert(());
+		let d = slotmap.insert(());
+		tree.insert_child(root1, TreeKey::null(), usize::MAX);
+		tree.insert_child(d, root1, usize::MAX);
+		assert_eq!(tree.validate(), Ok(()));
+		tree.storage.down_mut(root1).count = 2;
+		assert_eq!(tree.validate(), Err(TreeError::CountMismatch { node: root1, expected: 1, found: 2 }));
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn test_to_dot() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, a, usize::MAX);
+
+		let mut out = Vec::new();
+		tree.to_dot(&[root], &mut out, |k| format!("node{}", k.index())).unwrap();
+		let dot = String::from_utf8(out).unwrap();
+		assert!(dot.starts_with("digraph tree {"));
+		assert!(dot.contains(&format!("[label=\"node{} (layer 3)\"]", b.index())));
+		assert!(dot.contains(&format!("\"{:?}\" -> \"{:?}\";", root, a)));
+		assert!(dot.contains(&format!("\"{:?}\" -> \"{:?}\";", a, b)));
+		assert_eq!(dot.matches("->").count(), 2);
+	}
+
+	#[test]
+	fn test_first_child_count() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		assert_eq!(tree.descendant_count(root), 1);
+
+		// a从未设置过Down
+		assert!(tree.get_down(a).is_none());
+		tree.insert_child(b, a, usize::MAX);
+		assert_eq!(tree.descendant_count(a), 1);
+		assert_eq!(tree.child_count(a), 1);
+		assert_eq!(tree.descendant_count(root), 2);
+
+		// 不在树上的节点作为父节点
+		assert!(tree.get_down(c).is_none());
+		let d = slotmap.insert(());
+		tree.insert_child(d, c, usize::MAX);
+		assert_eq!(tree.descendant_count(c), 1);
+		tree.insert_child(c, root, usize::MAX);
+		assert_eq!(tree.descendant_count(root), 4);
+		assert_eq!(tree.validate_subtree(root), Ok(()));
+	}
+
+	#[test]
+	fn test_reorder() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(c, root, usize::MAX);
+		let children = |tree: &Tree<TreeKey, SlotMapTree>| tree.iter(tree.get_down(root).unwrap().head()).collect::<Vec<_>>();
+
+		// 第一个移到最后
+		tree.insert_child(a, root, usize::MAX);
+		assert_eq!(children(&tree), vec![b, c, a]);
+		assert_eq!(tree.get_down(root).unwrap().head(), b);
+		assert_eq!(tree.get_down(root).unwrap().tail(), a);
+		assert_eq!(tree.get_down(root).unwrap().len(), 3);
+		assert_eq!(tree.get_down(root).unwrap().count(), 3);
+
+		// 最后一个移到最前
+		tree.insert_child(a, root, 0);
+		assert_eq!(children(&tree), vec![a, b, c]);
+		assert_eq!(tree.get_down(root).unwrap().head(), a);
+		assert_eq!(tree.get_down(root).unwrap().tail(), c);
+
+		// 插入到原位置
+		tree.insert_child(c, root, usize::MAX);
+		tree.insert_child(b, root, 1);
+		assert_eq!(children(&tree), vec![a, b, c]);
+		assert_eq!(tree.validate_subtree(root), Ok(()));
+	}
+
+	#[test]
+	fn test_insert_children() {
+		let mut slotmap = SlotMap::default();
+		let root = slotmap.insert(());
+		let parent = slotmap.insert(());
+		let old = slotmap.insert(());
+		let children: Vec<TreeKey> = (0..100).map(|_| slotmap.insert(())).collect();
+		let sub = slotmap.insert(());
+
+		let build = |batch: bool| {
+			let mut tree: Tree<TreeKey, SlotMapTree> = Tree::new(SlotMapTree::default());
+			tree.insert_child(root, TreeKey::null(), usize::MAX);
+			tree.insert_child(parent, root, usize::MAX);
+			tree.insert_child(old, parent, usize::MAX);
+			// 带有子树的节点
+			tree.insert_child(sub, children[50], usize::MAX);
+			if batch {
+				tree.insert_children(parent, &children);
+			} else {
+				for c in children.iter() {
+					tree.insert_child(*c, parent, usize::MAX);
+				}
+			}
+			tree
+		};
+		let batch = build(true);
+		let single = build(false);
+
+		for k in [root, parent, old, sub].iter().chain(children.iter()) {
+			assert_eq!(format!("{:?}", batch.get_up(*k)), format!("{:?}", single.get_up(*k)));
+			assert_eq!(format!("{:?}", batch.get_down(*k)), format!("{:?}", single.get_down(*k)));
+			assert_eq!(format!("{:?}", batch.get_layer(*k)), format!("{:?}", single.get_layer(*k)));
+		}
+		assert_eq!(batch.descendant_count(root), 103);
+		assert_eq!(batch.depth(sub), Some(4));
+		assert_eq!(batch.validate_subtree(root), Ok(()));
+	}
+
+	#[test]
+	fn test_insert_children_invalid() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+
+		// 重复的节点、parent自身、parent的祖先、已有父节点的节点，都会panic，且树没有被修改
+		for children in [vec![b, c, b], vec![b, a], vec![b, root]] {
+			let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| tree.insert_children(a, &children)));
+
+			assert!(r.is_err());
+			assert!(tree.get_up(b).is_none());
+			assert!(tree.get_up(c).is_none());
+			assert_eq!(tree.child_count(a), 0);
+			assert_eq!(tree.validate(), Ok(()));
+		}
+		let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| tree.insert_children(root, &[b, a])));
+		assert!(r.is_err());
+		assert_eq!(tree.collect_children(root), vec![a]);
+		assert!(tree.get_up(b).is_none());
+
+		tree.insert_children(a, &[b, c]);
+		assert_eq!(tree.collect_children(a), vec![b, c]);
+		assert_eq!(tree.validate(), Ok(()));
+	}
+
+	#[test]
+	fn test_insert_links() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+
+		// 以不同的order插入，并与Vec::insert的结果对比
+		let mut expect: Vec<TreeKey> = Vec::new();
+		for (i, order) in [0, 0, 5, 1, 2, 3, 0, 100, 4, 6].iter().enumerate() {
+			let k = slotmap.insert(());
+			tree.insert_child(k, root, *order);
+			expect.insert((*order).min(i), k);
+		}
+		// 调整位置，order为调整前列表中的位置（插入到该位置的节点之前）
+		for order in [0, 9, 3, 7, 5, 6] {
+			let k = expect[5];
+			tree.insert_child(k, root, order);
+			let target = expect.get(order).copied();
+			if target != Some(k) {
+				expect.remove(5);
+				match target {
+					Some(target) => expect.insert(expect.iter().position(|r| *r == target).unwrap(), k),
+					None => expect.push(k),
+				}
+			}
+		}
+		assert_eq!(tree.iter(tree.get_down(root).unwrap().head()).collect::<Vec<_>>(), expect);
+
+		let mut back = Vec::new();
+		let mut k = tree.get_down(root).unwrap().tail();
+		while !k.is_null() {
+			back.push(k);
+			k = tree.get_up(k).unwrap().prev();
+		}
+		back.reverse();
+		assert_eq!(back, expect);
+		assert_eq!(tree.validate_subtree(root), Ok(()));
+	}
+
+	#[test]
+	fn test_with_capacity() {
+		let mut slotmap = SlotMap::default();
+		let keys: Vec<TreeKey> = (0..1000).map(|_| slotmap.insert(())).collect();
+
+		let mut tree1: Tree<TreeKey, SlotMapTree> = Tree::with_capacity(1000);
+		let mut tree2: Tree<TreeKey, SlotMapTree> = Tree::new(SlotMapTree::default());
+		for tree in [&mut tree1, &mut tree2] {
+			tree.insert_child(keys[0], TreeKey::null(), usize::MAX);
+			for i in 1..keys.len() {
+				tree.insert_child(keys[i], keys[(i - 1) / 4], i % 3);
+			}
+		}
+		for k in keys.iter() {
+			assert_eq!(format!("{:?}", tree1.get_up(*k)), format!("{:?}", tree2.get_up(*k)));
+			assert_eq!(format!("{:?}", tree1.get_down(*k)), format!("{:?}", tree2.get_down(*k)));
+			assert_eq!(format!("{:?}", tree1.get_layer(*k)), format!("{:?}", tree2.get_layer(*k)));
+		}
+		assert_eq!(tree1.validate_subtree(keys[0]), Ok(()));
+	}
+
+	#[test]
+	fn test_roots() {
+		let (mut tree, mut slotmap) = fixture();
+		let r1 = slotmap.insert(());
+		let r2 = slotmap.insert(());
+		let r3 = slotmap.insert(());
+		let a = slotmap.insert(());
+		tree.insert_child(r1, TreeKey::null(), usize::MAX);
+		tree.insert_child(r2, TreeKey::null(), usize::MAX);
+		tree.insert_child(r3, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, r1, usize::MAX);
+		// 重复设置为根节点
+		tree.insert_child(r2, TreeKey::null(), usize::MAX);
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![r1, r2, r3]);
+
+		tree.remove(a);
+		tree.remove(r2);
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![r1, r3]);
+
+		// 删除中间的根节点后，后面根节点的位置随之前移
+		tree.insert_child(r2, TreeKey::null(), usize::MAX);
+		tree.remove(r3);
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![r1, r2]);
+		tree.remove(r2);
+		tree.insert_child(r3, TreeKey::null(), usize::MAX);
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![r1, r3]);
+
+		tree.move_subtree(r3, r1, usize::MAX);
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![r1]);
+
+		tree.clear();
+		assert_eq!(tree.roots().count(), 0);
+	}
+
+	#[test]
+	fn test_insert_root_under_parent() {
+		let (mut tree, mut slotmap) = fixture();
+		let r1 = slotmap.insert(());
+		let r2 = slotmap.insert(());
+		let r3 = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let p = slotmap.insert(());
+		tree.insert_child(r1, TreeKey::null(), usize::MAX);
+		tree.insert_child(r2, TreeKey::null(), usize::MAX);
+		tree.insert_child(r3, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, r1, usize::MAX);
+		tree.insert_child(b, r2, usize::MAX);
+		tree.insert_child(c, r3, usize::MAX);
+		assert_eq!(tree.len(), 6);
+
+		// 根节点直接插入到另一棵树下
+		tree.insert_child(r2, r1, usize::MAX);
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![r1, r3]);
+		assert_eq!(tree.len(), 6);
+		assert_eq!((&tree).into_iter().count(), 6);
+		assert_eq!(tree.root_of(b), Some(r1));
+		assert_eq!(tree.validate_subtree(r1), Ok(()));
+
+		// 批量插入
+		tree.insert_children(a, &[r3]);
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![r1]);
+		assert_eq!(tree.len(), 6);
+		assert_eq!(tree.root_of(c), Some(r1));
+		assert_eq!(tree.validate_subtree(r1), Ok(()));
+
+		// 插入到不在树上的节点下，子树不再在树上
+		tree.remove(r2);
+		tree.insert_child(r2, TreeKey::null(), usize::MAX);
+		tree.insert_child(r2, p, usize::MAX);
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![r1]);
+		assert!(!tree.is_attached(r2) && !tree.is_attached(b));
+		assert!(!tree.is_empty());
+		assert_eq!(tree.len(), 4);
+	}
+
+	#[test]
+	fn test_remove_reinsert() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let other = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(other, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, a, usize::MAX);
+		tree.insert_child(c, b, usize::MAX);
+
+		tree.remove(a);
+		// 移除后，a的Down仍然指向原来的子节点
+		assert_eq!(tree.get_down(a).unwrap().head(), b);
+		assert_eq!(tree.get_down(a).unwrap().count(), 2);
+		assert_eq!(tree.depth(b), None);
+
+		// 再次插入a，原来的子树随之挂到新的位置
+		tree.insert_child(a, other, usize::MAX);
+		assert_eq!(tree.depth(c), Some(4));
+		assert_eq!(tree.root_of(c), Some(other));
+		assert_eq!(tree.descendant_count(other), 3);
+		assert_eq!(tree.descendant_count(root), 0);
+		assert_eq!(tree.validate_subtree(other), Ok(()));
+	}
+
+	#[test]
+	fn test_remove_recursive() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		let e = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, a, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+		tree.insert_child(d, c, usize::MAX);
+		tree.insert_child(e, root, usize::MAX);
+
+		tree.remove_recursive(a);
+		for k in [a, b, c, d] {
+			assert!(tree.get_up(k).is_none());
+			assert!(tree.get_down(k).is_none());
+			assert!(tree.get_layer(k).is_none());
+		}
+		assert_eq!(tree.descendant_count(root), 1);
+		assert_eq!(tree.first_child(root), Some(e));
+		assert_eq!(tree.validate_subtree(root), Ok(()));
+
+		tree.remove_recursive(root);
+		assert!(tree.get_down(root).is_none());
+		assert!(tree.get_up(e).is_none());
+		assert_eq!(tree.roots().count(), 0);
+	}
+
+	#[test]
+	fn test_try_insert_child() {
+		let (mut tree, mut slotmap) = fixture();
+		let r1 = slotmap.insert(());
+		let r2 = slotmap.insert(());
+		let a = slotmap.insert(());
+		tree.insert_child(r1, TreeKey::null(), usize::MAX);
+		tree.insert_child(r2, TreeKey::null(), usize::MAX);
+		assert_eq!(tree.try_insert_child(a, r1, usize::MAX), Ok(()));
+
+		assert_eq!(tree.try_insert_child(a, a, usize::MAX), Err(InsertError::SelfParent));
+		assert_eq!(tree.try_insert_child(a, r2, usize::MAX), Err(InsertError::AlreadyParented { existing: r1 }));
+		assert_eq!(tree.try_insert_child(a, TreeKey::null(), usize::MAX), Err(InsertError::AlreadyParented { existing: r1 }));
+		// 失败时树不变
+		assert_eq!(tree.parent(a), Some(r1));
+		assert_eq!(tree.descendant_count(r1), 1);
+		assert_eq!(tree.descendant_count(r2), 0);
+		// 同一个父节点下调整位置不是错误
+		assert_eq!(tree.try_insert_child(a, r1, 0), Ok(()));
+		// insert_child设置已有父节点的节点为根节点，只输出错误日志，不panic
+		tree.insert_child(a, TreeKey::null(), usize::MAX);
+		assert_eq!(tree.parent(a), Some(r1));
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![r1, r2]);
+	}
+
+	#[test]
+	fn test_insert_cycle() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, a, usize::MAX);
+		tree.insert_child(c, b, usize::MAX);
+
+		tree.remove(a);
+		// a已经脱离了树，但仍是c的祖先
+		assert_eq!(tree.try_insert_child(a, c, usize::MAX), Err(InsertError::Cycle));
+		assert_eq!(tree.parent(a), None);
+		assert_eq!(tree.descendant_count(c), 0);
+
+		// debug模式下insert_child同样会检查环
+		if cfg!(debug_assertions) {
+			let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| tree.insert_child(a, b, usize::MAX)));
+			assert!(r.is_err());
+		}
+	}
+
+	#[test]
+	fn test_find_child() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let c: Vec<TreeKey> = (0..5).map(|_| slotmap.insert(())).collect();
+		for k in c.iter() {
+			tree.insert_child(*k, root, usize::MAX);
+		}
+		let even = |k: TreeKey| c.iter().position(|r| *r == k).unwrap() % 2 == 0;
+
+		assert_eq!(tree.find_child(root, even), Some(c[0]));
+		assert_eq!(tree.rfind_child(root, even), Some(c[4]));
+		assert_eq!(tree.find_child(root, |k| k == c[3]), Some(c[3]));
+		assert_eq!(tree.rfind_child(root, |k| k == c[1]), Some(c[1]));
+		assert_eq!(tree.find_child(root, |_| false), None);
+		assert_eq!(tree.find_child(c[0], |_| true), None);
+	}
+
+	#[test]
+	fn test_nth_child() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let c: Vec<TreeKey> = (0..7).map(|_| slotmap.insert(())).collect();
+		for k in c.iter() {
+			tree.insert_child(*k, root, usize::MAX);
+		}
+
+		assert_eq!(tree.nth_child(root, 0), Some(c[0]));
+		assert_eq!(tree.nth_child(root, 3), Some(c[3]));
+		assert_eq!(tree.nth_child(root, 5), Some(c[5]));
+		assert_eq!(tree.nth_child(root, 6), Some(c[6]));
+		assert_eq!(tree.nth_child(root, 7), None);
+		assert_eq!(tree.nth_child(root, usize::MAX), None);
+		assert_eq!(tree.nth_child(c[0], 0), None);
+	}
+
+	#[test]
+	fn test_index_in_parent() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let other = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let c: Vec<TreeKey> = (0..5).map(|_| slotmap.insert(())).collect();
+		for k in c.iter() {
+			tree.insert_child(*k, root, usize::MAX);
+		}
+
+		for (i, k) in c.iter().enumerate() {
+			assert_eq!(tree.index_in_parent(*k), Some(i));
+			assert_eq!(tree.nth_child(root, i), Some(*k));
+		}
+		assert_eq!(tree.index_in_parent(root), None);
+		assert_eq!(tree.index_in_parent(other), None);
+	}
+
+	#[test]
+	fn test_children_double_ended() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let c: Vec<TreeKey> = (0..5).map(|_| slotmap.insert(())).collect();
+		for k in c.iter() {
+			tree.insert_child(*k, root, usize::MAX);
+		}
+
+		let mut rev = c.clone();
+		rev.reverse();
+		assert_eq!(ChildrenIterator::from_parent(tree.get_storage(), root).rev().collect::<Vec<_>>(), rev);
+		// 只有head时也可以反向迭代
+		assert_eq!(tree.iter(c[0]).rev().collect::<Vec<_>>(), rev);
+
+		// 两端交替迭代，相遇时结束
+		let mut it = ChildrenIterator::from_parent(tree.get_storage(), root);
+		assert_eq!(it.next(), Some(c[0]));
+		assert_eq!(it.next_back(), Some(c[4]));
+		assert_eq!(it.next_back(), Some(c[3]));
+		assert_eq!(it.next(), Some(c[1]));
+		assert_eq!(it.next(), Some(c[2]));
+		assert_eq!(it.next_back(), None);
+		assert_eq!(it.next(), None);
+
+		assert_eq!(ChildrenIterator::from_parent(tree.get_storage(), c[0]).next_back(), None);
+	}
+
+	#[test]
+	fn test_children_exact_size() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let c: Vec<TreeKey> = (0..4).map(|_| slotmap.insert(())).collect();
+		for k in c.iter() {
+			tree.insert_child(*k, root, usize::MAX);
+		}
+
+		let mut it = tree.children(root);
+		assert_eq!(it.len(), 4);
+		it.next();
+		it.next_back();
+		assert_eq!(it.size_hint(), (2, Some(2)));
+		assert_eq!(it.collect::<Vec<_>>(), vec![c[1], c[2]]);
+
+		// 只有头节点时不遍历链表，数量未知
+		assert_eq!(tree.iter(c[1]).size_hint(), (0, None));
+		assert_eq!(tree.iter(c[1]).count(), 3);
+		assert_eq!(tree.children(c[0]).len(), 0);
+		let v: Vec<TreeKey> = tree.children(root).collect();
+		assert_eq!(v, c);
+	}
+
+	#[test]
+	fn test_collect_children() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let c: Vec<TreeKey> = (0..3).map(|_| slotmap.insert(())).collect();
+		for k in c.iter() {
+			tree.insert_child(*k, root, usize::MAX);
+		}
+
+		let children = tree.collect_children(root);
+		assert_eq!(children, c);
+		for k in children {
+			tree.remove(k);
+		}
+		assert_eq!(tree.child_count(root), 0);
+		assert_eq!(tree.collect_children(root), Vec::new());
+	}
+
+	#[test]
+	fn test_path_to_root() {
+		let (mut tree, mut slotmap) = fixture();
+		let chain: Vec<TreeKey> = (0..4).map(|_| slotmap.insert(())).collect();
+		tree.insert_child(chain[0], TreeKey::null(), usize::MAX);
+		for i in 1..chain.len() {
+			tree.insert_child(chain[i], chain[i - 1], usize::MAX);
+		}
+
+		let up = tree.path_to_root(chain[3]);
+		assert_eq!(up, vec![chain[3], chain[2], chain[1], chain[0]]);
+		assert_eq!(up.capacity(), 4);
+		assert_eq!(tree.path_from_root(chain[3]), chain);
+		assert_eq!(tree.path_to_root(chain[0]), vec![chain[0]]);
+		assert_eq!(tree.path_to_root(TreeKey::null()), Vec::new());
+	}
+
+	#[test]
+	fn test_relative_path() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let c1 = slotmap.insert(());
+		let c2 = slotmap.insert(());
+		let c3 = slotmap.insert(());
+		let c4 = slotmap.insert(());
+		let c5 = slotmap.insert(());
+		let root2 = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(c1, root, usize::MAX);
+		tree.insert_child(c2, c1, usize::MAX);
+		tree.insert_child(c3, c2, usize::MAX);
+		tree.insert_child(c4, root, usize::MAX);
+		tree.insert_child(c5, c4, usize::MAX);
+		tree.insert_child(root2, TreeKey::null(), usize::MAX);
+
+		// ../../../c4/c5
+		assert_eq!(tree.relative_path(c3, c5), Some(vec![PathStep::Up, PathStep::Up, PathStep::Up, PathStep::Down(c4), PathStep::Down(c5)]));
+		assert_eq!(tree.relative_path(c5, c3), Some(vec![PathStep::Up, PathStep::Up, PathStep::Down(c1), PathStep::Down(c2), PathStep::Down(c3)]));
+		assert_eq!(tree.relative_path(c3, c1), Some(vec![PathStep::Up, PathStep::Up]));
+		assert_eq!(tree.relative_path(root, c2), Some(vec![PathStep::Down(c1), PathStep::Down(c2)]));
+		assert_eq!(tree.relative_path(c2, c2), Some(Vec::new()));
+		assert_eq!(tree.relative_path(c2, root2), None);
+	}
+
+	#[test]
+	fn test_insert_before_after() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let c1 = slotmap.insert(());
+		let c2 = slotmap.insert(());
+		let c3 = slotmap.insert(());
+		let c4 = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(c2, root, usize::MAX);
+		tree.insert_before(c1, c2);
+		tree.insert_after(c4, c2);
+		tree.insert_after(c3, c2);
+
+		assert_eq!(tree.collect_children(root), vec![c1, c2, c3, c4]);
+		assert_eq!(tree.child_count(root), 4);
+		assert!(tree.validate_subtree(root).is_ok());
+
+		let c5 = slotmap.insert(());
+		let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| tree.insert_after(c5, root)));
+		assert!(r.is_err());
+	}
+
+	#[test]
+	fn test_tree_len() {
+		let (mut tree, mut slotmap) = fixture();
+		assert_eq!(tree.len(), 0);
+
+		let root = slotmap.insert(());
+		let root2 = slotmap.insert(());
+		let c1 = slotmap.insert(());
+		let c2 = slotmap.insert(());
+		let c3 = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(root2, TreeKey::null(), usize::MAX);
+		tree.insert_child(c1, root, usize::MAX);
+		tree.insert_child(c2, c1, usize::MAX);
+		tree.insert_child(c3, root2, usize::MAX);
+		assert_eq!(tree.len(), 5);
+
+		// 移除的子树不再计入
+		tree.remove(c1);
+		assert_eq!(tree.len(), 3);
+		tree.remove(root2);
+		assert_eq!(tree.len(), 1);
+	}
+
+	#[test]
+	fn test_is_attached_exists() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let c1 = slotmap.insert(());
+		let c2 = slotmap.insert(());
+		let other = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(c1, root, usize::MAX);
+		tree.insert_child(c2, c1, usize::MAX);
+
+		assert!(tree.is_attached(root) && tree.exists(root));
+		assert!(tree.is_attached(c2) && tree.exists(c2));
+		assert!(!tree.is_attached(other) && !tree.exists(other));
+
+		// 移除后c1保留Down，c2保留Up，但都不在树上
+		tree.remove(c1);
+		assert!(!tree.is_attached(c1) && tree.exists(c1));
+		assert!(!tree.is_attached(c2) && tree.exists(c2));
+
+		tree.remove_recursive(c1);
+		assert!(!tree.exists(c1) && !tree.exists(c2));
+	}
+
+	#[test]
+	fn test_subtree_keys() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let c1 = slotmap.insert(());
+		let c2 = slotmap.insert(());
+		let c3 = slotmap.insert(());
+		let c4 = slotmap.insert(());
+		let sibling = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(c1, root, usize::MAX);
+		tree.insert_child(c2, c1, usize::MAX);
+		tree.insert_child(c3, c1, usize::MAX);
+		tree.insert_child(c4, root, usize::MAX);
+		tree.insert_child(sibling, root, usize::MAX);
+
+		let keys = tree.subtree_keys(root);
+		assert_eq!(keys, vec![root, c1, c2, c3, c4, sibling]);
+		assert_eq!(keys.len(), tree.descendant_count(root) + 1);
+		assert_eq!(keys.capacity(), keys.len());
+
+		// 不包含子树根节点的兄弟节点
+		let keys = tree.subtree_keys(c1);
+		assert_eq!(keys, vec![c1, c2, c3]);
+		assert_eq!(keys.len(), tree.descendant_count(c1) + 1);
+		assert_eq!(tree.subtree_keys(c4), vec![c4]);
+	}
+
+	#[test]
+	fn test_recursive_iter_sequences() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, a, usize::MAX);
+		tree.insert_child(c, root, usize::MAX);
+
+		assert_eq!(tree.recursive_iter(root).collect::<Vec<_>>(), vec![root, a, b, c]);
+		// 不包含a的兄弟节点c
+		assert_eq!(tree.recursive_iter(a).collect::<Vec<_>>(), vec![a, b]);
+		assert_eq!(tree.recursive_iter(c).collect::<Vec<_>>(), vec![c]);
+		assert_eq!(tree.recursive_iter(TreeKey::null()).count(), 0);
+
+		assert_eq!(tree.recursive_iter_children(root).collect::<Vec<_>>(), vec![a, b, c]);
+		assert_eq!(tree.recursive_iter_children(a).collect::<Vec<_>>(), vec![b]);
+		assert_eq!(tree.recursive_iter_children(c).count(), 0);
+		assert_eq!(tree.recursive_iter_children(TreeKey::null()).count(), 0);
+	}
+
+	#[test]
+	fn test_depth_histogram() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		// root -> 3个子节点，每个子节点 -> 2个子节点，第一个孙节点 -> 1个子节点
+		let mut first_grandchild = TreeKey::null();
+		for _ in 0..3 {
+			let c = slotmap.insert(());
+			tree.insert_child(c, root, usize::MAX);
+			for _ in 0..2 {
+				let g = slotmap.insert(());
+				tree.insert_child(g, c, usize::MAX);
+				if first_grandchild.is_null() {
+					first_grandchild = g;
+				}
+			}
+		}
+		tree.insert_child(slotmap.insert(()), first_grandchild, usize::MAX);
+
+		assert_eq!(tree.depth_histogram(root), vec![1, 3, 6, 1]);
+		// count_at_depth按Layer::layer统计，根节点为第1层
+		assert_eq!(tree.count_at_depth(root, 0), 0);
+		assert_eq!(tree.count_at_depth(root, 1), 1);
+		assert_eq!(tree.count_at_depth(root, 2), 3);
+		assert_eq!(tree.count_at_depth(root, 3), 6);
+		assert_eq!(tree.count_at_depth(root, 4), 1);
+		assert_eq!(tree.count_at_depth(root, 5), 0);
+		assert_eq!(tree.count_at_depth(first_grandchild, 3), 1);
+		assert_eq!(tree.count_at_depth(first_grandchild, 4), 1);
+		assert_eq!(tree.count_at_depth(first_grandchild, 2), 0);
+		assert_eq!(tree.depth_histogram(first_grandchild), vec![1, 1]);
+		assert_eq!(tree.depth_histogram(TreeKey::null()), Vec::<usize>::new());
+		assert_eq!(tree.count_at_depth(TreeKey::null(), 1), 0);
+
+		// 不在树上的子树没有layer
+		tree.detach(first_grandchild);
+		assert_eq!(tree.count_at_depth(first_grandchild, 3), 0);
+		assert_eq!(tree.depth_histogram(first_grandchild), vec![1, 1]);
+	}
+
+	#[test]
+	fn test_boxed_dyn_storage() {
+		#[allow(unused_mut)]
+		let mut storages: Vec<Box<dyn StorageMut<TreeKey>>> = vec![Box::new(SlotMapTree::default())];
+		#[cfg(feature = "std")]
+		storages.push(Box::new(crate::HashMapTree::default()));
+		for storage in storages {
+			let mut tree: Tree<TreeKey, Box<dyn StorageMut<TreeKey>>> = Tree::new(storage);
+
+			let mut slotmap = SlotMap::default();
+			let root = slotmap.insert(());
+			let c1 = slotmap.insert(());
+			let c2 = slotmap.insert(());
+			tree.insert_child(root, TreeKey::null(), usize::MAX);
+			tree.insert_child(c1, root, usize::MAX);
+			tree.insert_child(c2, c1, usize::MAX);
+
+			assert_eq!(tree.roots().collect::<Vec<_>>(), vec![root]);
+			assert_eq!(tree.subtree_keys(root), vec![root, c1, c2]);
+			assert_eq!(tree.depth(c2), Some(3));
+			assert!(tree.validate_subtree(root).is_ok());
+
+			tree.remove(c1);
+			assert_eq!(tree.len(), 1);
+		}
+	}
+
+	#[test]
+	fn test_children_enumerated() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let c: Vec<TreeKey> = (0..4).map(|_| slotmap.insert(())).collect();
+		for k in c.iter() {
+			tree.insert_child(*k, root, usize::MAX);
+		}
+
+		let it = tree.children_enumerated(root);
+		assert_eq!(it.len(), 4);
+		assert_eq!(it.collect::<Vec<_>>(), c.iter().copied().enumerate().collect::<Vec<_>>());
+		assert_eq!(tree.children_enumerated(c[0]).len(), 0);
+	}
+
+	#[test]
+	fn test_replace_node() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let c1 = slotmap.insert(());
+		let c2 = slotmap.insert(());
+		let c3 = slotmap.insert(());
+		let g1 = slotmap.insert(());
+		let g2 = slotmap.insert(());
+		let gg = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(c1, root, usize::MAX);
+		tree.insert_child(c2, root, usize::MAX);
+		tree.insert_child(c3, root, usize::MAX);
+		tree.insert_child(g1, c2, usize::MAX);
+		tree.insert_child(g2, c2, usize::MAX);
+		tree.insert_child(gg, g1, usize::MAX);
+
+		// 替换中间节点
+		let n2 = slotmap.insert(());
+		tree.replace_node(c2, n2);
+		assert!(!tree.exists(c2) && !tree.is_attached(c2));
+		assert_eq!(tree.collect_children(root), vec![c1, n2, c3]);
+		assert_eq!(tree.collect_children(n2), vec![g1, g2]);
+		assert_eq!(tree.parent(g1), Some(n2));
+		assert_eq!(tree.parent(gg), Some(g1));
+		assert_eq!(tree.depth(n2), Some(2));
+		assert!(tree.validate_subtree(root).is_ok());
+
+		// 替换头尾节点
+		let n1 = slotmap.insert(());
+		let n3 = slotmap.insert(());
+		tree.replace_node(c1, n1);
+		tree.replace_node(c3, n3);
+		assert_eq!(tree.collect_children(root), vec![n1, n2, n3]);
+		assert_eq!(tree.collect_children(root).into_iter().rev().collect::<Vec<_>>(), tree.children(root).rev().collect::<Vec<_>>());
+		assert!(tree.validate_subtree(root).is_ok());
+
+		// 替换根节点
+		let new_root = slotmap.insert(());
+		tree.replace_node(root, new_root);
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![new_root]);
+		assert_eq!(tree.root_of(gg), Some(new_root));
+		assert_eq!(tree.depth(gg), Some(4));
+		assert_eq!(tree.descendant_count(new_root), 6);
+		assert!(tree.validate_subtree(new_root).is_ok());
+	}
+
+	#[test]
+	fn test_recompute_layers_counts() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let c1 = slotmap.insert(());
+		let c2 = slotmap.insert(());
+		let g1 = slotmap.insert(());
+		let g2 = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(c1, root, usize::MAX);
+		tree.insert_child(c2, root, usize::MAX);
+		tree.insert_child(g1, c1, usize::MAX);
+		tree.insert_child(g2, g1, usize::MAX);
+
+		// 破坏layer后修复
+		edit_layer(&mut tree, g1, |layer| layer.layer = 7);
+		edit_layer(&mut tree, g2, |layer| layer.root = g2);
+		edit_layer(&mut tree, root, |layer| layer.layer = 3);
+		assert!(tree.validate_subtree(root).is_err());
+		tree.recompute_layers(root);
+		assert!(tree.validate_subtree(root).is_ok());
+		assert_eq!(tree.depth(root), Some(1));
+		assert_eq!(tree.depth(g2), Some(4));
+		assert_eq!(tree.root_of(g2), Some(root));
+
+		// 只修复子树
+		edit_layer(&mut tree, g2, |layer| layer.layer = 1);
+		tree.recompute_layers(g1);
+		assert_eq!(tree.depth(g2), Some(4));
+
+		// 破坏count后修复
+		tree.storage.down_mut(root).count = 100;
+		tree.storage.down_mut(c1).count = 0;
+		tree.storage.down_mut(g1).count = 9;
+		assert!(tree.validate_subtree(root).is_err());
+		tree.recompute_counts(root);
+		assert!(tree.validate_subtree(root).is_ok());
+		assert_eq!(tree.descendant_count(root), 4);
+		assert_eq!(tree.descendant_count(c1), 2);
+		assert_eq!(tree.descendant_count(g1), 1);
+	}
+
+	#[test]
+	fn test_into_iter_forest() {
+		let (mut tree, mut slotmap) = fixture();
+		assert_eq!((&tree).into_iter().count(), 0);
+
+		let r1 = slotmap.insert(());
+		let r2 = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		tree.insert_child(r1, TreeKey::null(), usize::MAX);
+		tree.insert_child(r2, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, r1, usize::MAX);
+		tree.insert_child(b, a, usize::MAX);
+		tree.insert_child(c, r1, usize::MAX);
+		tree.insert_child(d, r2, usize::MAX);
+
+		let mut keys = Vec::new();
+		for k in &tree {
+			keys.push(k);
+		}
+		assert_eq!(keys, vec![r1, a, b, c, r2, d]);
+		assert_eq!(keys.len(), tree.len());
+	}
+
+	#[test]
+	fn test_drain_subtree() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		let e = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, a, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+		tree.insert_child(d, b, usize::MAX);
+		tree.insert_child(e, root, usize::MAX);
+
+		let keys = tree.drain_subtree(a);
+		assert_eq!(keys, vec![d, b, c, a]);
+		for k in keys {
+			assert!(!tree.exists(k) && !tree.is_attached(k));
+		}
+		assert_eq!(tree.collect_children(root), vec![e]);
+		assert_eq!(tree.descendant_count(root), 1);
+		assert!(tree.validate_subtree(root).is_ok());
+	}
+
+	#[test]
+	fn test_reattach() {
+		let (mut tree, mut slotmap) = fixture();
+		let r1 = slotmap.insert(());
+		let r2 = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		let e = slotmap.insert(());
+		tree.insert_child(r1, TreeKey::null(), usize::MAX);
+		tree.insert_child(r2, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, r1, usize::MAX);
+		tree.insert_child(b, r1, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+		tree.insert_child(d, c, usize::MAX);
+		tree.insert_child(e, r2, usize::MAX);
+
+		// 同层同根：c从a移动到b下
+		tree.reattach(c, b, 0);
+		assert_eq!(tree.collect_children(b), vec![c]);
+		assert_eq!(tree.child_count(a), 0);
+		assert_eq!((tree.depth(c), tree.depth(d)), (Some(3), Some(4)));
+		assert_eq!(tree.root_of(d), Some(r1));
+		assert!(tree.validate_subtree(r1).is_ok());
+
+		// 同层不同根：c移动到另一棵树的e下
+		tree.reattach(c, e, 0);
+		assert_eq!((tree.depth(c), tree.depth(d)), (Some(3), Some(4)));
+		assert_eq!((tree.root_of(c), tree.root_of(d)), (Some(r2), Some(r2)));
+		assert_eq!(tree.descendant_count(r1), 2);
+		assert_eq!(tree.descendant_count(r2), 3);
+		assert!(tree.validate_subtree(r1).is_ok());
+		assert!(tree.validate_subtree(r2).is_ok());
+
+		// 层数改变：c直接挂到r1下
+		tree.reattach(c, r1, 0);
+		assert_eq!(tree.collect_children(r1), vec![c, a, b]);
+		assert_eq!((tree.depth(c), tree.depth(d)), (Some(2), Some(3)));
+		assert_eq!(tree.root_of(d), Some(r1));
+		assert!(tree.validate_subtree(r1).is_ok());
+		assert!(tree.validate_subtree(r2).is_ok());
+
+		// 断开后挂回同一层：利用子树保留的layer，只修改Layer::root
+		tree.detach(c);
+		assert!(!tree.is_attached(c));
+		assert_eq!(tree.depth(d), Some(3));
+		tree.reattach(c, r2, 0);
+		assert_eq!(tree.collect_children(r2), vec![c, e]);
+		assert_eq!((tree.depth(c), tree.depth(d)), (Some(2), Some(3)));
+		assert_eq!((tree.root_of(c), tree.root_of(d)), (Some(r2), Some(r2)));
+		assert_eq!(tree.descendant_count(r1), 2);
+		assert!(tree.validate().is_ok());
+
+		// 断开后挂到不同的层
+		tree.detach(c);
+		tree.reattach(c, a, 0);
+		assert_eq!((tree.depth(c), tree.depth(d)), (Some(3), Some(4)));
+		assert_eq!((tree.root_of(c), tree.root_of(d)), (Some(r1), Some(r1)));
+		assert_eq!(tree.descendant_count(r1), 4);
+		assert_eq!(tree.descendant_count(r2), 1);
+		assert!(tree.validate().is_ok());
+	}
+
+	#[test]
+	fn test_has_children_is_leaf() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let other = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, a, usize::MAX);
+
+		assert!(tree.has_children(root) && !tree.is_leaf(root));
+		assert!(!tree.has_children(b) && tree.is_leaf(b));
+
+		// 移除唯一的子节点后，a仍有Down，但len为0
+		tree.remove(b);
+		assert!(tree.get_down(a).is_some());
+		assert_eq!(tree.get_down(a).unwrap().len(), 0);
+		assert!(!tree.has_children(a) && tree.is_leaf(a));
+
+		// 不在树上的节点不是叶子节点
+		assert!(!tree.has_children(b) && !tree.is_leaf(b));
+		assert!(!tree.has_children(other) && !tree.is_leaf(other));
+	}
+
+	#[test]
+	fn test_first_last_leaf() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let a1 = slotmap.insert(());
+		let a2 = slotmap.insert(());
+		let a11 = slotmap.insert(());
+		let b = slotmap.insert(());
+		let b1 = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(a1, a, usize::MAX);
+		tree.insert_child(a11, a1, usize::MAX);
+		tree.insert_child(a2, a, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(b1, b, usize::MAX);
+
+		assert_eq!(tree.first_leaf(root), a11);
+		assert_eq!(tree.last_leaf(root), b1);
+		assert_eq!(tree.first_leaf(a), a11);
+		assert_eq!(tree.last_leaf(a), a2);
+		assert_eq!(tree.first_leaf(a2), a2);
+		assert_eq!(tree.last_leaf(a2), a2);
+
+		// 子节点被移除后，节点自身成为叶子
+		tree.remove(b1);
+		assert_eq!(tree.last_leaf(root), b);
+	}
+
+	#[test]
+	fn test_next_prev_preorder() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let a1 = slotmap.insert(());
+		let a11 = slotmap.insert(());
+		let a2 = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let c1 = slotmap.insert(());
+		let c11 = slotmap.insert(());
+		let root2 = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(root2, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(a1, a, usize::MAX);
+		tree.insert_child(a11, a1, usize::MAX);
+		tree.insert_child(a2, a, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(c, root, usize::MAX);
+		tree.insert_child(c1, c, usize::MAX);
+		tree.insert_child(c11, c1, usize::MAX);
+
+		let order: Vec<TreeKey> = tree.recursive_iter(root).collect();
+		assert_eq!(order, vec![root, a, a1, a11, a2, b, c, c1, c11]);
+
+		// 逐个向后、向前步进，与先序迭代的结果一致
+		for i in 0..order.len() {
+			assert_eq!(tree.next_preorder(order[i]), order.get(i + 1).copied());
+			assert_eq!(tree.prev_preorder(order[i]), if i == 0 { None } else { Some(order[i - 1]) });
+		}
+
+		// 从任意节点开始，完整地向后、向前走一遍
+		let mut forward = vec![root];
+		while let Some(k) = tree.next_preorder(*forward.last().unwrap()) {
+			forward.push(k);
+		}
+		assert_eq!(forward, order);
+		let mut backward = vec![c11];
+		while let Some(k) = tree.prev_preorder(*backward.last().unwrap()) {
+			backward.push(k);
+		}
+		backward.reverse();
+		assert_eq!(backward, order);
+
+		// 不跨越到其他树
+		assert_eq!(tree.next_preorder(root2), None);
+		assert_eq!(tree.prev_preorder(root2), None);
+		assert_eq!(tree.next_preorder(TreeKey::null()), None);
+	}
+
+	#[test]
+	fn test_common_ancestor() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let a1 = slotmap.insert(());
+		let a2 = slotmap.insert(());
+		let a21 = slotmap.insert(());
+		let b = slotmap.insert(());
+		let root2 = slotmap.insert(());
+		let other = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(root2, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(a1, a, usize::MAX);
+		tree.insert_child(a2, a, usize::MAX);
+		tree.insert_child(a21, a2, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+
+		assert_eq!(tree.common_ancestor(&[]), None);
+		assert_eq!(tree.common_ancestor(&[a21]), Some(a21));
+		assert_eq!(tree.common_ancestor(&[other]), None);
+		assert_eq!(tree.common_ancestor(&[a1, a21]), Some(a));
+		assert_eq!(tree.common_ancestor(&[a1, a21, a2]), Some(a));
+		assert_eq!(tree.common_ancestor(&[a21, a1, b]), Some(root));
+		assert_eq!(tree.common_ancestor(&[a2, a21]), Some(a2));
+		assert_eq!(tree.common_ancestor(&[a1, a21, root2]), None);
+		assert_eq!(tree.common_ancestor(&[a1, other]), None);
+	}
+
+	#[test]
+	fn test_insert_target() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		let e = slotmap.insert(());
+		tree.insert(root, Target::Root);
+		tree.insert(b, Target::ChildOf { parent: root, order: usize::MAX });
+		tree.insert(d, Target::ChildOf { parent: root, order: 0 });
+		tree.insert(a, Target::Before(d));
+		tree.insert(c, Target::After(b));
+		tree.insert(e, Target::ChildOf { parent: c, order: 0 });
+
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![root]);
+		assert_eq!(tree.collect_children(root), vec![a, d, b, c]);
+		assert_eq!(tree.collect_children(c), vec![e]);
+		assert_eq!(tree.depth(e), Some(3));
+		assert!(tree.validate_subtree(root).is_ok());
+	}
+
+	#[test]
+	fn test_clone_subtree() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let a1 = slotmap.insert(());
+		let a2 = slotmap.insert(());
+		let a21 = slotmap.insert(());
+		let b = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(a1, a, usize::MAX);
+		tree.insert_child(a2, a, usize::MAX);
+		tree.insert_child(a21, a2, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+
+		let mut mapping = std::collections::HashMap::new();
+		let new_a = tree.clone_subtree(a, |old| {
+			let k = slotmap.insert(());
+			mapping.insert(old, k);
+			k
+		});
+		assert_eq!(mapping.len(), 4);
+		assert_eq!(mapping[&a], new_a);
+
+		// 新子树不在树上，原子树不变
+		assert!(!tree.is_attached(new_a));
+		assert_eq!(tree.collect_children(root), vec![a, b]);
+		assert_eq!(tree.descendant_count(root), 5);
+
+		// 挂到树上后，结构与原子树一致
+		tree.insert_child(new_a, b, usize::MAX);
+		let cloned: Vec<TreeKey> = tree.recursive_iter(new_a).collect();
+		let expected: Vec<TreeKey> = tree.recursive_iter(a).map(|k| mapping[&k]).collect();
+		assert_eq!(cloned, expected);
+		for k in tree.recursive_iter(a).skip(1).collect::<Vec<_>>() {
+			assert_eq!(tree.parent(mapping[&k]), tree.parent(k).map(|p| mapping[&p]));
+		}
+		assert_eq!(tree.descendant_count(new_a), tree.descendant_count(a));
+		assert_eq!(tree.depth(mapping[&a21]), Some(5));
+		assert!(tree.validate_subtree(root).is_ok());
+	}
+
+	#[test]
+	fn test_node_info() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let other = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+
+		assert!(tree.node_info(other).is_none());
+
+		let info = tree.node_info(root).unwrap();
+		assert!(info.up.is_none());
+		assert_eq!(info.down.unwrap().len(), 2);
+		assert_eq!(info.layer.as_ref().unwrap().layer(), 1);
+
+		let info = tree.node_info(b).unwrap();
+		let up = info.up.unwrap();
+		assert_eq!((up.parent(), up.prev(), up.next()), (root, a, TreeKey::null()));
+		assert_eq!(info.layer.as_ref().unwrap().root(), root);
+		assert!(!format!("{:?}", info).is_empty());
+
+		// 移除后，节点自身只保留Down，子节点只保留Up和Down
+		let c = slotmap.insert(());
+		tree.insert_child(c, a, usize::MAX);
+		tree.remove(a);
+		let info = tree.node_info(a).unwrap();
+		assert!(info.up.is_none() && info.down.is_some() && info.layer.is_none());
+		let info = tree.node_info(c).unwrap();
+		assert!(info.up.is_some() && info.layer.is_none());
+	}
+
+	#[test]
+	fn test_freeze_counts() {
+		let mut slotmap = SlotMap::default();
+		let keys: Vec<TreeKey> = (0..40).map(|_| slotmap.insert(())).collect();
+
+		// 同样的操作分别在冻结与不冻结的情况下执行
+		let build = |tree: &mut Tree<TreeKey, SlotMapTree>| {
+			tree.insert_child(keys[0], TreeKey::null(), usize::MAX);
+			tree.insert_child(keys[1], keys[0], usize::MAX);
+			tree.insert_child(keys[2], keys[1], usize::MAX);
+			for i in 3..40 {
+				tree.insert_child(keys[i], keys[i % 3], usize::MAX);
+			}
+			for i in (5..40).step_by(7) {
+				tree.remove(keys[i]);
+			}
+			tree.move_subtree(keys[4], keys[6], 0);
+		};
+
+		let mut normal: Tree<TreeKey, SlotMapTree> = Tree::new(SlotMapTree::default());
+		build(&mut normal);
+
+		let mut frozen: Tree<TreeKey, SlotMapTree> = Tree::new(SlotMapTree::default());
+		frozen.freeze_counts();
+		build(&mut frozen);
+		frozen.unfreeze_counts();
+
+		assert!(normal.validate_subtree(keys[0]).is_ok());
+		assert!(frozen.validate_subtree(keys[0]).is_ok());
+		for k in normal.recursive_iter(keys[0]).collect::<Vec<_>>() {
+			assert_eq!(frozen.descendant_count(k), normal.descendant_count(k));
+		}
+		assert_eq!(frozen.len(), normal.len());
+
+		// 冻结期间在子树中插入多层节点后再移除子树，父节点的count不能下溢
+		let (r, a, x, y, b) = (keys[0], keys[1], keys[2], keys[3], keys[4]);
+		let mut tree: Tree<TreeKey, SlotMapTree> = Tree::new(SlotMapTree::default());
+		tree.insert_child(r, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, r, usize::MAX);
+		tree.freeze_counts();
+		tree.insert_child(x, a, usize::MAX);
+		tree.insert_child(y, x, usize::MAX);
+		tree.remove(a);
+		tree.unfreeze_counts();
+		assert_eq!(tree.descendant_count(r), 0);
+		assert!(tree.validate_subtree(r).is_ok());
+		tree.insert_child(b, r, usize::MAX);
+
+		// 移动count已过时的子树
+		tree.freeze_counts();
+		tree.insert_child(a, b, usize::MAX);
+		tree.insert_child(keys[5], y, usize::MAX);
+		tree.move_subtree(a, r, 0);
+		tree.swap(a, b);
+		tree.unfreeze_counts();
+		assert_eq!(tree.descendant_count(r), 5);
+		assert!(tree.validate_subtree(r).is_ok());
+	}
+
+	#[test]
+	fn test_sibling_count() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let other = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let c: Vec<TreeKey> = (0..7).map(|_| slotmap.insert(())).collect();
+		for k in c.iter() {
+			tree.insert_child(*k, root, usize::MAX);
+		}
+		let g = slotmap.insert(());
+		tree.insert_child(g, c[2], usize::MAX);
+
+		assert_eq!(tree.sibling_count(root), 1);
+		assert_eq!(tree.sibling_count(c[0]), 7);
+		assert_eq!(tree.sibling_count(c[6]), 7);
+		assert_eq!(tree.sibling_count(g), 1);
+		assert_eq!(tree.sibling_count(other), 0);
+
+		tree.remove(c[3]);
+		assert_eq!(tree.sibling_count(c[0]), 6);
+		assert_eq!(tree.sibling_count(c[3]), 0);
+	}
+
+	#[cfg(feature = "layer_u32")]
+	#[test]
+	fn test_layer_u32() {
+		assert_eq!(std::mem::size_of::<crate::LayerIndex>(), 4);
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, a, usize::MAX);
+
+		assert_eq!(tree.get_layer(b).unwrap().layer(), 3u32);
+		assert_eq!(tree.depth(b), Some(3));
+		tree.remove(a);
+		assert_eq!(tree.depth(b), None);
+		assert!(tree.validate_subtree(root).is_ok());
+	}
+
+	#[test]
+	fn test_move_prev_next() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let c: Vec<TreeKey> = (0..4).map(|_| slotmap.insert(())).collect();
+		for k in c.iter() {
+			tree.insert_child(*k, root, usize::MAX);
+		}
+		let g = slotmap.insert(());
+		tree.insert_child(g, c[1], usize::MAX);
+
+		// 边界上不移动
+		tree.move_prev(c[0]);
+		tree.move_next(c[3]);
+		tree.move_prev(root);
+		assert_eq!(tree.collect_children(root), vec![c[0], c[1], c[2], c[3]]);
+
+		// 中间节点向前、向后移动
+		tree.move_prev(c[2]);
+		assert_eq!(tree.collect_children(root), vec![c[0], c[2], c[1], c[3]]);
+		tree.move_next(c[1]);
+		assert_eq!(tree.collect_children(root), vec![c[0], c[2], c[3], c[1]]);
+		assert!(tree.validate_subtree(root).is_ok());
+
+		// 移动到头尾
+		tree.move_prev(c[2]);
+		assert_eq!(tree.collect_children(root), vec![c[2], c[0], c[3], c[1]]);
+		assert_eq!(tree.first_child(root), Some(c[2]));
+		assert_eq!(tree.last_child(root), Some(c[1]));
+		assert_eq!(tree.children(root).rev().collect::<Vec<_>>(), vec![c[1], c[3], c[0], c[2]]);
+		assert_eq!(tree.descendant_count(root), 5);
+		assert_eq!(tree.depth(g), Some(3));
+		assert!(tree.validate_subtree(root).is_ok());
+	}
+
+	#[test]
+	fn test_recursive_iter_with_depth() {
+		let (mut tree, mut slotmap) = fixture();
+		let mut names = std::collections::HashMap::new();
+		let mut key = |name: &'static str| {
+			let k = slotmap.insert(());
+			names.insert(k, name);
+			k
+		};
+		let root = key("root");
+		let a = key("a");
+		let a1 = key("a1");
+		let a2 = key("a2");
+		let b = key("b");
+		let b1 = key("b1");
+		let b11 = key("b11");
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(a1, a, usize::MAX);
+		tree.insert_child(a2, a, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(b1, b, usize::MAX);
+		tree.insert_child(b11, b1, usize::MAX);
+
+		let mut s = String::new();
+		for (depth, k) in tree.recursive_iter_with_depth(root) {
+			s += &format!("{}{}\n", "  ".repeat(depth), names[&k]);
+		}
+		assert_eq!(s, "root\n  a\n    a1\n    a2\n  b\n    b1\n      b11\n");
+
+		// 深度相对于传入的节点
+		assert_eq!(tree.recursive_iter_with_depth(b).collect::<Vec<_>>(), vec![(0, b), (1, b1), (2, b11)]);
+		assert_eq!(tree.recursive_iter_with_depth(TreeKey::null()).count(), 0);
+	}
+
+	#[test]
+	fn test_insert_as_root_fixes_count() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let a1 = slotmap.insert(());
+		let a11 = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(a1, a, usize::MAX);
+
+		// 断开后在冻结count期间修改子树，a的count不再正确（unfreeze只修正根节点所在的树）
+		tree.detach(a);
+		tree.freeze_counts();
+		tree.insert_child(a11, a1, usize::MAX);
+		tree.unfreeze_counts();
+		assert_eq!(tree.descendant_count(a), 1);
+
+		// 作为根节点插入时修正count
+		tree.insert_child(a, TreeKey::null(), usize::MAX);
+		assert_eq!(tree.descendant_count(a), 2);
+		assert_eq!(tree.depth(a11), Some(3));
+		assert_eq!(tree.len(), 4);
+
+		// 直接破坏count后重新作为根节点插入
+		tree.detach(a);
+		tree.storage.down_mut(a).count = 99;
+		tree.insert_child(a, TreeKey::null(), usize::MAX);
+		assert_eq!(tree.descendant_count(a), 2);
+	}
+
+	#[test]
+	fn test_attached_children() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let detached = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let c: Vec<TreeKey> = (0..4).map(|_| slotmap.insert(())).collect();
+		for k in c.iter() {
+			tree.insert_child(*k, root, usize::MAX);
+		}
+		// detached不在树上，其子节点也都不在树上
+		let d0 = slotmap.insert(());
+		let d1 = slotmap.insert(());
+		tree.insert_child(d0, detached, usize::MAX);
+		tree.insert_child(d1, detached, usize::MAX);
+		assert_eq!(tree.children(detached).count(), 2);
+		assert_eq!(tree.attached_children(detached).count(), 0);
+
+		// 手动删除部分子节点的layer，模拟部分构建的树
+		tree.storage.remove_layer(c[1]);
+		tree.storage.remove_layer(c[3]);
+		assert_eq!(tree.collect_children(root), c);
+		assert_eq!(tree.attached_children(root).collect::<Vec<_>>(), vec![c[0], c[2]]);
+	}
+
+	#[test]
+	fn test_compute_intervals() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let mut keys = vec![root];
+		for i in 1..30 {
+			let k = slotmap.insert(());
+			// 父节点取之前的某个节点，得到形状不规则的树
+			tree.insert_child(k, keys[(i * 5 + 3) % i], usize::MAX);
+			keys.push(k);
+		}
+
+		let iv = tree.compute_intervals(root);
+		assert_eq!(iv.len(), keys.len());
+		assert_eq!(*iv.get(root).unwrap(), (0, keys.len() as u32 - 1));
+		for a in keys.iter() {
+			let ia = *iv.get(*a).unwrap();
+			assert_eq!((ia.1 - ia.0) as usize, tree.descendant_count(*a));
+			for b in keys.iter() {
+				let ib = *iv.get(*b).unwrap();
+				assert_eq!(interval_contains(ia, ib), a == b || tree.is_ancestor(*a, *b));
+			}
+		}
+
+		// 只计算子树
+		let sub = tree.compute_intervals(keys[1]);
+		assert_eq!(sub.len(), tree.descendant_count(keys[1]) + 1);
+		assert!(sub.get(root).is_none());
+	}
+
+	#[test]
+	fn test_remove_and_promote_children() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let b1 = slotmap.insert(());
+		let b2 = slotmap.insert(());
+		let b21 = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(c, root, usize::MAX);
+		tree.insert_child(b1, b, usize::MAX);
+		tree.insert_child(b2, b, usize::MAX);
+		tree.insert_child(b21, b2, usize::MAX);
+
+		// 中间位置
+		tree.remove_and_promote_children(b);
+		assert!(!tree.exists(b));
+		assert_eq!(tree.collect_children(root), vec![a, b1, b2, c]);
+		assert_eq!(tree.children(root).rev().collect::<Vec<_>>(), vec![c, b2, b1, a]);
+		assert_eq!(tree.parent(b1), Some(root));
+		assert_eq!((tree.depth(b1), tree.depth(b2), tree.depth(b21)), (Some(2), Some(2), Some(3)));
+		assert_eq!(tree.descendant_count(root), 5);
+		assert!(tree.validate_subtree(root).is_ok());
+
+		// 头部位置，并且带有祖先节点
+		tree.move_subtree(b2, a, 0);
+		assert_eq!(tree.collect_children(a), vec![b2]);
+		assert_eq!(tree.depth(b21), Some(4));
+		tree.remove_and_promote_children(b2);
+		assert_eq!(tree.collect_children(a), vec![b21]);
+		assert_eq!(tree.depth(b21), Some(3));
+		assert_eq!(tree.descendant_count(a), 1);
+		assert_eq!(tree.descendant_count(root), 4);
+		assert!(tree.validate_subtree(root).is_ok());
+
+		// 没有子节点时，等同于删除
+		tree.remove_and_promote_children(c);
+		assert_eq!(tree.collect_children(root), vec![a, b1]);
+		assert!(tree.validate_subtree(root).is_ok());
+
+		// 根节点，子节点都成为根节点
+		tree.remove_and_promote_children(root);
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![a, b1]);
+		assert_eq!(tree.depth(b21), Some(2));
+		assert_eq!(tree.root_of(b21), Some(a));
+		assert!(tree.validate_subtree(a).is_ok());
+		assert!(!tree.exists(root));
+
+		// 已断开的节点，子节点成为断开的子树，不成为根节点
+		let d = slotmap.insert(());
+		let d1 = slotmap.insert(());
+		let d11 = slotmap.insert(());
+		tree.insert_child(d, a, usize::MAX);
+		tree.insert_child(d1, d, usize::MAX);
+		tree.insert_child(d11, d1, usize::MAX);
+		tree.remove(d);
+		tree.remove_and_promote_children(d);
+
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![a, b1]);
+		assert_eq!(tree.parent(d1), None);
+		assert!(tree.storage.get_layer(d1).is_none());
+		assert!(!tree.exists(d));
+		assert_eq!(tree.collect_children(d1), vec![d11]);
+		tree.insert_child(d1, a, usize::MAX);
+		assert_eq!(tree.depth(d11), Some(3));
+		assert!(tree.validate_subtree(a).is_ok());
+	}
+
+	#[test]
+	fn test_ensure_root() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		tree.ensure_root(root);
+		tree.insert_child(a, root, usize::MAX);
+		tree.ensure_root(root);
+
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![root]);
+		assert_eq!(tree.depth(root), Some(1));
+		assert_eq!(tree.collect_children(root), vec![a]);
+		assert_eq!(tree.descendant_count(root), 1);
+
+		// 移除后再次确保为根节点
+		tree.remove(root);
+		assert_eq!(tree.roots().count(), 0);
+		tree.ensure_root(root);
+		tree.ensure_root(root);
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![root]);
+		assert_eq!(tree.depth(a), Some(2));
+		assert!(tree.validate_subtree(root).is_ok());
+	}
+
+	#[test]
+	fn test_ancestors_until() {
+		let (mut tree, mut slotmap) = fixture();
+		let chain: Vec<TreeKey> = (0..5).map(|_| slotmap.insert(())).collect();
+		tree.insert_child(chain[0], TreeKey::null(), usize::MAX);
+		for i in 1..chain.len() {
+			tree.insert_child(chain[i], chain[i - 1], usize::MAX);
+		}
+		let other = slotmap.insert(());
+		tree.insert_child(other, chain[1], usize::MAX);
+
+		assert_eq!(tree.ancestors_until(chain[4], chain[1]).collect::<Vec<_>>(), vec![chain[3], chain[2]]);
+		assert_eq!(tree.ancestors_until(chain[4], chain[0]).collect::<Vec<_>>(), vec![chain[3], chain[2], chain[1]]);
+		assert_eq!(tree.ancestors_until(chain[4], chain[3]).count(), 0);
+
+		// stop不是祖先节点
+		assert_eq!(tree.ancestors_until(chain[4], other).count(), 0);
+		assert_eq!(tree.ancestors_until(chain[1], chain[4]).count(), 0);
+		assert_eq!(tree.ancestors_until(chain[4], chain[4]).count(), 0);
+		assert_eq!(tree.ancestors_until(chain[4], TreeKey::null()).count(), 0);
+	}
+
+	#[test]
+	fn test_set_checks() {
+		let (mut tree, mut slotmap) = fixture();
+		assert_eq!(tree.checks_enabled(), cfg!(debug_assertions));
+
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, a, usize::MAX);
+
+		// 开启检查后，无论是否为debug模式，形成环的插入都会失败
+		tree.set_checks(true);
+		assert!(tree.checks_enabled());
+		tree.detach(a);
+		let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| tree.insert_child(a, b, 0)));
+		assert!(r.is_err());
+		let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| tree.insert_child(a, a, 0)));
+		assert!(r.is_err());
+		assert_eq!(tree.parent(a), None);
+		assert_eq!(tree.parent(b), Some(a));
+
+		tree.set_checks(false);
+		assert_eq!(tree.checks_enabled(), cfg!(debug_assertions));
+		tree.insert_child(a, root, 0);
+		assert!(tree.validate_subtree(root).is_ok());
+	}
+
+	#[test]
+	fn test_next_same_layer() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		let a1 = slotmap.insert(());
+		let a2 = slotmap.insert(());
+		let c1 = slotmap.insert(());
+		let d1 = slotmap.insert(());
+		let a11 = slotmap.insert(());
+		let d11 = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		for k in [a, b, c, d] {
+			tree.insert_child(k, root, usize::MAX);
+		}
+		tree.insert_child(a1, a, usize::MAX);
+		tree.insert_child(a2, a, usize::MAX);
+		tree.insert_child(c1, c, usize::MAX);
+		tree.insert_child(d1, d, usize::MAX);
+		tree.insert_child(a11, a1, usize::MAX);
+		tree.insert_child(d11, d1, usize::MAX);
+
+		// 按先序得到每一层的节点，逐个向后、向前步进应得到同样的序列
+		for depth in 1..=4 {
+			let layer: Vec<TreeKey> = tree.recursive_iter(root).filter(|k| tree.depth(*k) == Some(depth)).collect();
+			for i in 0..layer.len() {
+				assert_eq!(tree.next_same_layer(layer[i]), layer.get(i + 1).copied());
+				assert_eq!(tree.prev_same_layer(layer[i]), if i == 0 { None } else { Some(layer[i - 1]) });
+			}
+		}
+
+		// 跨越父节点，且跳过没有该层节点的子树
+		assert_eq!(tree.next_same_layer(a2), Some(c1));
+		assert_eq!(tree.next_same_layer(a11), Some(d11));
+		assert_eq!(tree.prev_same_layer(d11), Some(a11));
+		assert_eq!(tree.prev_same_layer(c1), Some(a2));
+		assert_eq!(tree.next_same_layer(root), None);
+	}
+
+	#[test]
+	fn test_iter_children() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), usize::MAX);
+		let c: Vec<TreeKey> = (0..3).map(|_| slotmap.insert(())).collect();
+		for k in c.iter() {
+			tree.insert_child(*k, root, usize::MAX);
+		}
+
+		let head = tree.get_down(root).unwrap().head();
+		assert_eq!(tree.iter_children(root).collect::<Vec<_>>(), tree.iter(head).collect::<Vec<_>>());
+		assert_eq!(tree.iter_children(root).collect::<Vec<_>>(), c);
+		assert_eq!(tree.iter_children(c[0]).count(), 0);
+		assert_eq!(tree.iter_children(TreeKey::null()).count(), 0);
+	}
+
+	#[test]
+	fn test_on_structure_change() {
+		use std::sync::{Arc, Mutex};
+
+		let (mut tree, mut slotmap) = fixture();
+		let events = Arc::new(Mutex::new(Vec::new()));
+		let events1 = events.clone();
+		tree.on_structure_change(Box::new(move |e| events1.lock().unwrap().push(e)));
+
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), 0);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_after(c, b);
+		tree.move_next(a);
+		tree.move_subtree(c, a, 0);
+		tree.reattach(c, b, 0);
+		tree.remove(b);
+		assert_eq!(*events.lock().unwrap(), vec![
+			StructureEvent::Attached { node: root, parent: TreeKey::null() },
+			StructureEvent::Attached { node: a, parent: root },
+			StructureEvent::Attached { node: b, parent: root },
+			StructureEvent::Attached { node: c, parent: root },
+			StructureEvent::Reordered { parent: root },
+			StructureEvent::Reparented { node: c, old_parent: root, new_parent: a },
+			StructureEvent::Reparented { node: c, old_parent: a, new_parent: b },
+			StructureEvent::Detached { node: b, parent: root },
+		]);
+
+		// 在同一个父节点下调整位置，只通知顺序变化
+		events.lock().unwrap().clear();
+		let d = slotmap.insert(());
+		tree.insert_child(d, root, usize::MAX);
+		tree.insert_child(d, root, 0);
+		tree.insert_before(a, d);
+		assert_eq!(*events.lock().unwrap(), vec![
+			StructureEvent::Attached { node: d, parent: root },
+			StructureEvent::Reordered { parent: root },
+			StructureEvent::Reordered { parent: root },
+		]);
+		assert_eq!(tree.collect_children(root), vec![a, d]);
+
+		// 复制的子树不在树上，不产生通知
+		events.lock().unwrap().clear();
+		let copy = tree.clone_subtree(root, |_| slotmap.insert(()));
+		assert!(events.lock().unwrap().is_empty());
+		assert_eq!(tree.descendant_count(copy), 2);
+
+		// 取消回调后不再通知
+		events.lock().unwrap().clear();
+		tree.clear_structure_change();
+		tree.remove(a);
+		assert!(events.lock().unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_on_structure_change_not_send() {
+		use std::{cell::RefCell, rc::Rc};
+
+		// 回调类型不要求Send，可以捕获Rc
+		type LocalListener = Box<dyn FnMut(StructureEvent<TreeKey>)>;
+		let mut tree: Tree<TreeKey, SlotMapTree, LocalListener> = Tree::new(SlotMapTree::default());
+		let mut slotmap: SlotMap<TreeKey, ()> = SlotMap::default();
+		let events = Rc::new(RefCell::new(Vec::new()));
+		let events1 = events.clone();
+		tree.on_structure_change(Box::new(move |e| events1.borrow_mut().push(e)));
+
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), 0);
+		tree.insert_child(a, root, 0);
+		tree.remove(a);
+		assert_eq!(*events.borrow(), vec![
+			StructureEvent::Attached { node: root, parent: TreeKey::null() },
+			StructureEvent::Attached { node: a, parent: root },
+			StructureEvent::Detached { node: a, parent: root },
+		]);
+
+		// 转移存储时回调一起转移
+		let mut tree = tree.transfer_into(SlotMapTree::default(), &[root]);
+		tree.remove(root);
+		assert_eq!(events.borrow().len(), 4);
+	}
+
+	#[test]
+	fn test_are_siblings() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let root1 = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), 0);
+		tree.insert_child(root1, TreeKey::null(), 0);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+
+		assert!(tree.are_siblings(a, b));
+		assert!(tree.are_siblings(b, a));
+		assert!(!tree.are_siblings(a, a));
+		assert!(!tree.are_siblings(a, c));
+		// 根节点之间不是兄弟节点
+		assert!(!tree.are_siblings(root, root1));
+
+		// 移除后不在树上
+		tree.remove(b);
+		assert!(!tree.are_siblings(a, b));
+	}
+
+	#[test]
+	fn test_is_root() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let root1 = slotmap.insert(());
+		let a = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), 0);
+		tree.insert_child(root1, TreeKey::null(), 0);
+		tree.insert_child(a, root, 0);
+
+		assert!(tree.is_root(root));
+		assert!(tree.is_root(root1));
+		assert!(!tree.is_root(a));
+		tree.remove(a);
+		assert!(!tree.is_root(a));
+
+		// 根节点的Layer::root不是自身
+		edit_layer(&mut tree, root1, |layer| layer.root = root);
+		assert!(!tree.is_root(root1));
+		assert_eq!(tree.validate_subtree(root1), Err(TreeError::RootMismatch { node: root1, layer: 1, root }));
+		edit_layer(&mut tree, root1, |layer| layer.root = root1);
+		assert_eq!(tree.validate_subtree(root1), Ok(()));
+	}
+
+	#[test]
+	fn test_insert_node_with_layer() {
+		let mut slotmap = SlotMap::default();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+
+		let mut expected: Tree<TreeKey, SlotMapTree> = Tree::new(SlotMapTree::default());
+		expected.insert_child(root, TreeKey::null(), 0);
+		expected.insert_child(a, root, usize::MAX);
+		expected.insert_child(b, root, usize::MAX);
+		expected.insert_child(c, a, usize::MAX);
+
+		// 按已知的层数和根节点加载
+		let mut tree: Tree<TreeKey, SlotMapTree> = Tree::new(SlotMapTree::default());
+		tree.insert_child(root, TreeKey::null(), 0);
+		tree.insert_node_with_layer(a, root, Layer::new(2, root), TreeKey::null(), TreeKey::null());
+		tree.insert_node_with_layer(b, root, Layer::new(2, root), a, TreeKey::null());
+		tree.insert_node_with_layer(c, a, Layer::new(3, root), TreeKey::null(), TreeKey::null());
+		assert_eq!(tree.validate_subtree(root), Ok(()));
+
+		for k in [root, a, b, c] {
+			assert_eq!(tree.get_up(k).map(|up| (up.parent(), up.prev(), up.next())), expected.get_up(k).map(|up| (up.parent(), up.prev(), up.next())));
+			assert_eq!(tree.get_down(k).map(|d| (d.head(), d.tail(), d.len(), d.count())), expected.get_down(k).map(|d| (d.head(), d.tail(), d.len(), d.count())));
+			assert_eq!(tree.get_layer(k).map(|l| (l.layer(), l.root())), expected.get_layer(k).map(|l| (l.layer(), l.root())));
 		}
-		if next.is_null() {
-			p_down.tail = id;
+	}
+
+	#[test]
+	fn test_iter_with_parent() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), 0);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+
+		let pairs: Vec<(TreeKey, TreeKey)> = tree.iter_with_parent(root).collect();
+		assert_eq!(pairs, vec![(root, TreeKey::null()), (a, root), (c, a), (b, root)]);
+		for (node, parent) in pairs.iter().skip(1) {
+			assert_eq!(tree.get_up(*node).unwrap().parent(), *parent);
 		}
-		p_down.len += 1;
-		p_down.count += count;
-		self.storage.set_down(parent, p_down);
 
-		let p_p = self.storage.get_up(parent).map_or(K::null(), |p|{p.parent});
-		// 递归向上修改count
-		self.modify_count(p_p, count as isize);
+		// 子树的根节点，父节点返回null
+		assert_eq!(tree.iter_with_parent(a).collect::<Vec<_>>(), vec![(a, TreeKey::null()), (c, a)]);
+	}
 
-		// layer.layer.is_null(), 并且不是同层调整时，才递归设置layer
-        if !layer.layer.is_null() && count > 0 {
-            self.insert_tree(fix_prev, Layer {layer: layer.layer + 1, root: layer.root.clone()});
-			// 再次设置当前节点的layer，表明该节点是作为挂在主树上的一个子树的根
-			self.storage.set_layer(id, layer);
+	#[test]
+	fn test_recursive_iter_in() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), 0);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+
+		let mut buf = Vec::new();
+		for node in [root, a, b, c, root] {
+			let nodes: Vec<TreeKey> = tree.recursive_iter_in(node, &mut buf).collect();
+			assert_eq!(nodes, tree.recursive_iter(node).collect::<Vec<_>>());
 		}
-    }
+		// 中途停止的迭代残留在buf中的数据，会在下次构造时清空
+		assert_eq!(tree.recursive_iter_in(root, &mut buf).next(), Some(root));
+		assert_eq!(tree.recursive_iter_in(c, &mut buf).collect::<Vec<_>>(), vec![c]);
+		assert_eq!(tree.recursive_iter_in(TreeKey::null(), &mut buf).count(), 0);
+		assert!(buf.capacity() > 0);
+	}
 
-	/// 创建一个根节点
-	fn insert_as_root(&mut self, id: K) {
-        // 设置为根节点
-		match self.storage.get_up(id) {
-			// 将节点作为根节点插入到树失败，节点已经存在一个父
-			Some(up) if !up.parent.is_null() => {
-				out_any!(log::error, "insert_root fail, node has a parent, id: {:?}, parent: {:?}", id, up.parent);
-				return;
-			},
-			_ => {
-				self.storage.set_root(id);
-				self.storage.set_layer(id, Layer {layer: 1, root: id});
-				let head = match self.storage.get_down(id) {
-					Some(down) => down.head,
-					None => {
-						self.storage.set_down(id, Down {
-							head: K::null(),
-							tail: K::null(),
-							len: 0,
-							count: 0,
-						});
-						K::null()
-					}
-				};
-				self.insert_tree(head, Layer {layer: 2, root: id});
-				self.storage.set_layer(id, Layer {layer: 1, root: id}); // 设置第二遍，表明为子树的根
-			},
-		};
-    }
-	
-    // 插入到树上， 就是递归设置每个子节点的layer
-	// 安全：调用该方法，确保!layer.is_null()
-    fn insert_tree(&mut self, mut id: K, layer: Layer<K>) {
-        while !id.is_null() {
-            let head = {
-				self.storage.set_layer(id, layer.clone());
-                let head = self.storage.get_down(id).map_or(K::null(), |down|{down.head});
-				if let Some(up) = self.storage.get_up(id) {
-					id = up.next;
-				} else {
-					id = K::null();
-				}
-				head
-            };
-            self.insert_tree(head, Layer {layer: layer.layer + 1, root: layer.root});
-        }
-    }
-    // 从树上移除， 就是递归设置每个子节点, 删除layer
-    fn remove_tree(&mut self, mut id: K) {
-        while !id.is_null() {
-            self.storage.remove_layer(id); // 删除layer
+	#[test]
+	fn test_layer_root_after_reparent() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let root1 = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), 0);
+		tree.insert_child(root1, TreeKey::null(), 0);
+		tree.insert_child(d, root1, 0);
+		tree.insert_child(a, root, 0);
+		tree.insert_child(b, a, 0);
+		tree.insert_child(c, b, 0);
 
-			if let Some(down) = self.storage.get_down(id) {
-				// 如果存在子节点，则递归删除layer
-				let head = down.head;
-				self.remove_tree(head);
-				id = self.storage.up(id).next;
-			} else {
-				break;
-			}
+		let moved = [a, b, c];
+		// 层数改变
+		tree.move_subtree(a, d, 0);
+		for k in moved {
+			assert_eq!(tree.root_of(k), Some(root1));
 		}
-    }
-    // // 递归销毁
-    // fn recursive_destroy(&mut self, parent: K, mut id: K) {
-	// 	self.storage.delete_children(parent);
-    //     while !id.is_null() {
-	// 		if let Some(down) = self.storage.get_children(id) {
-	// 			self.recursive_destroy(id, down.head);
-	// 		};
-    //         id = self.storage.parent(id).next;
-    //     }
-    // }
+		assert_eq!(tree.validate_subtree(root1), Ok(()));
+		// 层数不变，只修改root
+		tree.reattach(a, root, 0);
+		for k in moved {
+			assert_eq!(tree.root_of(k), Some(root));
+		}
+		assert_eq!(tree.validate_subtree(root), Ok(()));
+		// 不同父节点间交换
+		tree.swap(a, d);
+		for k in moved {
+			assert_eq!(tree.root_of(k), Some(root1));
+		}
+		assert_eq!(tree.root_of(d), Some(root));
+		assert_eq!(tree.validate_subtree(root), Ok(()));
+		assert_eq!(tree.validate_subtree(root1), Ok(()));
 
-    // 递归向上，修改节点的count
-    fn modify_count(&mut self, mut id: K, count: isize) {
-        while !id.is_null() {
-			let down = self.storage.down_mut(id);
-			down.count = (down.count as isize + count) as usize;
-			// 除了修改，需要发通知吗？TODO
-			if let Some(up) = self.storage.get_up_mut(id) {
-				id = up.parent;
-			} else {
-				break;
-			}
-        }
-    }
-    // 移除节点
-    fn remove_node(&mut self, id: K, parent: K, count: usize, prev: K, next: K) {
-        // 修改prev和next的节点
-        if !prev.is_null() {
-            let node = self.storage.up_mut(prev);
-			node.next = next;
-        }
-        if !next.is_null() {
-            let node = self.storage.up_mut(next);
-			node.prev = prev;
-        }
-        
-		// 修改parent的children, count
-		let p_down = self.storage.down_mut(parent) ;
-		if prev.is_null() {
-			p_down.head = next;
+		edit_layer(&mut tree, c, |layer| layer.root = root);
+		assert_eq!(tree.validate_subtree(root1), Err(TreeError::LayerRootMismatch { node: c, expected: root1, found: root }));
+		assert_eq!(tree.validate_subtree(b), Err(TreeError::LayerRootMismatch { node: c, expected: root1, found: root }));
+	}
+
+	#[test]
+	fn test_remove_many() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		let e = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), 0);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, a, usize::MAX);
+		tree.insert_child(c, b, usize::MAX);
+		tree.insert_child(d, root, usize::MAX);
+		tree.insert_child(e, root, usize::MAX);
+
+		// c、b是a的递归子节点，d重复出现
+		let removed = tree.remove_many(&[c, d, a, b, d]);
+		assert_eq!(removed, vec![d, a, b, c]);
+		assert_eq!(tree.collect_children(root), vec![e]);
+		assert_eq!(tree.get_down(root).unwrap().count(), 1);
+		assert_eq!(tree.validate_subtree(root), Ok(()));
+		// a的子树结构保留，与remove相同
+		assert_eq!(tree.get_up(c).unwrap().parent(), b);
+		assert!(!tree.is_attached(c));
+
+		assert_eq!(tree.remove_many(&[]), vec![]);
+		// 已经不在树上的节点被跳过
+		assert_eq!(tree.remove_many(&[a, c]), vec![]);
+
+		// 大量嵌套的选择
+		let chain: Vec<TreeKey> = (0..1000).map(|_| slotmap.insert(())).collect();
+		tree.insert_child(chain[0], e, usize::MAX);
+		for i in 1..chain.len() {
+			tree.insert_child(chain[i], chain[i - 1], usize::MAX);
 		}
-		if next.is_null() {
-			p_down.tail = prev;
+		let mut selected = chain.clone();
+		selected.reverse();
+		assert_eq!(tree.remove_many(&selected), chain);
+		assert_eq!(tree.get_down(root).unwrap().count(), 1);
+		assert_eq!(tree.validate_subtree(root), Ok(()));
+	}
+
+	#[test]
+	fn test_deep_chain() {
+		let (mut tree, mut slotmap) = fixture();
+		let chain: Vec<TreeKey> = (0..100_000).map(|_| slotmap.insert(())).collect();
+		// 自底向上链接，此时链不在树上，插入不需要修改祖先的count
+		for i in (1..chain.len()).rev() {
+			tree.insert_child(chain[i], chain[i - 1], 0);
 		}
-		p_down.len -= 1;
-		p_down.count -= count;
+		// 作为根节点插入时，设置整条链的layer
+		tree.insert_child(chain[0], TreeKey::null(), 0);
+		assert_eq!(tree.get_down(chain[0]).unwrap().count(), chain.len() - 1);
+		assert_eq!(tree.get_layer(chain[chain.len() - 1]).unwrap().layer(), chain.len() as LayerIndex);
+		assert_eq!(tree.root_of(chain[chain.len() - 1]), Some(chain[0]));
 
-		let p_p = self.storage.get_up(parent).map_or(K::null(), |up|{up.parent});
-            
+		// 移除时，删除整条链的layer
+		tree.remove(chain[0]);
+		assert!(chain.iter().all(|k| !tree.is_attached(*k)));
+	}
 
-        // 递归向上修改count
-		self.modify_count(p_p, -(count as isize));
+	#[test]
+	fn test_remove_preview_count() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), 0);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, a, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+		tree.insert_child(d, root, usize::MAX);
 
-		// 设置节点的层为None
-		self.storage.remove_layer(id);
+		assert_eq!(tree.remove_preview_count(root), 5);
+		assert_eq!(tree.remove_preview_count(b), 1);
 
-		// 设置up信息为null
-		self.storage.remove_up(id);
-    }
-}
+		let count = tree.remove_preview_count(a);
+		assert_eq!(count, 3);
+		assert_eq!(tree.drain_subtree(a).len(), count);
+		assert_eq!(tree.remove_preview_count(a), 0);
 
-// pub struct ChildrenMutIterator<'a, K: Null, S: Storage<K>> {
-//     inner: &'a mut S,
-//     head: K,
-// }
-// impl<'a, K: Null, S: Storage<K>> Iterator for ChildrenMutIterator<'a, K, S> {
-//     type Item = K;
-//     fn next(&mut self) -> Option<Self::Item> {
-// 		if self.head.is_null() {
-// 			return None;
-// 		}
-// 		let head = self.head;
+		let count = tree.remove_preview_count(root);
+		assert_eq!(tree.drain_subtree(root).len(), count);
+	}
 
-//         let inner = unsafe { &mut *(self.inner as *mut S) };
-//         let n = unsafe { inner.get_unchecked_mut(head) };
-//         let next = n.next;
-//         let r = Some((head, n));
-//         self.head = next;
-//         r
-//     }
-// }
-pub struct ChildrenIterator<'a, K: Null + Copy + Clone, S: Storage<K>>{
-    inner: &'a S,
-    head: K,
-}
+	#[test]
+	fn test_set_child_order() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), 0);
+		let c: Vec<TreeKey> = (0..5).map(|_| slotmap.insert(())).collect();
+		for k in c.iter() {
+			tree.insert_child(*k, root, usize::MAX);
+		}
+		tree.insert_child(slotmap.insert(()), c[2], 0);
 
-impl<'a, K: Null + Copy + Clone, S: Storage<K>> ChildrenIterator<'a, K, S> {
-	pub fn new(s: &'a S, head: K) -> Self {
-		ChildrenIterator {
-			inner: s,
-			head
+		let mut reversed = c.clone();
+		reversed.reverse();
+		tree.set_child_order(root, &reversed);
+		assert_eq!(tree.collect_children(root), reversed);
+		assert_eq!(tree.validate_subtree(root), Ok(()));
+
+		let shuffled = vec![c[3], c[0], c[4], c[2], c[1]];
+		tree.set_child_order(root, &shuffled);
+		assert_eq!(tree.collect_children(root), shuffled);
+		assert_eq!(tree.iter_children(root).rev().collect::<Vec<_>>(), vec![c[1], c[2], c[4], c[0], c[3]]);
+		assert_eq!(tree.validate_subtree(root), Ok(()));
+	}
+
+	#[test]
+	#[should_panic]
+	fn test_set_child_order_not_permutation() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), 0);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| tree.set_child_order(root, &[a, a])));
+		assert!(r.is_err());
+		// 检查失败后，临时的标记已恢复，树保持不变
+		assert_eq!(tree.parent(a), Some(root));
+		assert_eq!(tree.collect_children(root), vec![a, b]);
+		assert_eq!(tree.validate(), Ok(()));
+		tree.set_child_order(root, &[b, root]);
+	}
+
+	#[test]
+	fn test_format_subtree() {
+		let (mut tree, mut slotmap) = fixture();
+		let names = ["root", "a", "b", "c", "d", "e"];
+		let keys: Vec<TreeKey> = names.iter().map(|_| slotmap.insert(())).collect();
+		let (root, a, b, c, d, e) = (keys[0], keys[1], keys[2], keys[3], keys[4], keys[5]);
+		tree.insert_child(root, TreeKey::null(), 0);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+		tree.insert_child(d, c, usize::MAX);
+		tree.insert_child(e, a, usize::MAX);
+
+		let label = |k: TreeKey| names[keys.iter().position(|x| *x == k).unwrap()].to_string();
+		assert_eq!(tree.format_subtree(root, label), "\
+root
+├── a
+│   ├── c
+│   │   └── d
+│   └── e
+└── b
+");
+		assert_eq!(tree.format_subtree(c, label), "c\n└── d\n");
+		assert_eq!(tree.format_subtree(TreeKey::null(), label), "");
+	}
+
+	#[test]
+	fn test_is_empty() {
+		let (mut tree, mut slotmap) = fixture();
+		assert!(tree.is_empty());
+		assert_eq!(tree.len(), 0);
+
+		let root = slotmap.insert(());
+		let root1 = slotmap.insert(());
+		let a = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), 0);
+		tree.insert_child(a, root, 0);
+		tree.insert_child(root1, TreeKey::null(), 0);
+		assert!(!tree.is_empty());
+		assert_eq!(tree.len(), 3);
+		assert_eq!(tree.roots().count(), 2);
+
+		tree.remove(root);
+		assert!(!tree.is_empty());
+		assert_eq!(tree.len(), 1);
+
+		tree.remove(root1);
+		assert!(tree.is_empty());
+		assert_eq!(tree.len(), 0);
+		assert_eq!(tree.roots().count(), 0);
+	}
+
+	#[test]
+	fn test_insert_child_from_back() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), 0);
+		let c: Vec<TreeKey> = (0..3).map(|_| slotmap.insert(())).collect();
+		for k in c.iter() {
+			tree.insert_child(*k, root, usize::MAX);
 		}
+
+		// offset为0，插入到最后
+		let x = slotmap.insert(());
+		tree.insert_child_from_back(x, root, 0);
+		assert_eq!(tree.collect_children(root), vec![c[0], c[1], c[2], x]);
+		tree.remove(x);
+
+		// 倒数第二个位置
+		tree.insert_child_from_back(x, root, 1);
+		assert_eq!(tree.collect_children(root), vec![c[0], c[1], x, c[2]]);
+		tree.remove(x);
+
+		// offset等于长度，插入到最前
+		tree.insert_child_from_back(x, root, 3);
+		assert_eq!(tree.collect_children(root), vec![x, c[0], c[1], c[2]]);
+		tree.remove(x);
+
+		// offset超过长度，同样插入到最前
+		tree.insert_child_from_back(x, root, 100);
+		assert_eq!(tree.collect_children(root), vec![x, c[0], c[1], c[2]]);
+		assert_eq!(tree.validate_subtree(root), Ok(()));
+
+		// 没有子节点的父节点
+		let y = slotmap.insert(());
+		tree.insert_child_from_back(y, c[0], 2);
+		assert_eq!(tree.collect_children(c[0]), vec![y]);
 	}
-}
 
-impl<'a, K: Null + Copy + Clone, S: Storage<K>> Iterator for ChildrenIterator<'a, K, S> {
-    type Item = K;
+	#[test]
+	fn test_from_parent_pointers() {
+		let mut slotmap = SlotMap::default();
+		let k: Vec<TreeKey> = (0..7).map(|_| slotmap.insert(())).collect();
+		// 两棵树：k0 -> [k2 -> [k4], k3]，k1 -> [k5, k6]，子节点出现在父节点之前
+		let pairs = vec![
+			(k[4], Some(k[2])),
+			(k[0], None),
+			(k[2], Some(k[0])),
+			(k[5], Some(k[1])),
+			(k[1], None),
+			(k[3], Some(k[0])),
+			(k[6], Some(k[1])),
+		];
+		let tree = Tree::from_parent_pointers(&pairs).unwrap();
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![k[0], k[1]]);
+		assert_eq!(tree.collect_children(k[0]), vec![k[2], k[3]]);
+		assert_eq!(tree.collect_children(k[2]), vec![k[4]]);
+		assert_eq!(tree.collect_children(k[1]), vec![k[5], k[6]]);
+		assert_eq!(tree.depth(k[4]), Some(3));
+		assert_eq!(tree.root_of(k[4]), Some(k[0]));
+		assert_eq!(tree.len(), 7);
+		assert_eq!(tree.validate_subtree(k[0]), Ok(()));
+		assert_eq!(tree.validate_subtree(k[1]), Ok(()));
 
-    fn next(&mut self) -> Option<Self::Item> {
-		if self.head.is_null() {
-			return None;
+		assert_eq!(Tree::from_parent_pointers(&[(k[0], None), (k[0], None)]).err(), Some(ParentPointerError::DuplicateNode { node: k[0] }));
+		assert_eq!(Tree::from_parent_pointers(&[(k[0], None), (k[1], Some(k[2]))]).err(), Some(ParentPointerError::DanglingParent { node: k[1], parent: k[2] }));
+		assert_eq!(Tree::from_parent_pointers(&[(k[0], None), (k[1], Some(k[2])), (k[2], Some(k[1])), (k[3], Some(k[1]))]).err(), Some(ParentPointerError::Cycle { node: k[1] }));
+		assert_eq!(Tree::from_parent_pointers(&[(k[0], Some(k[0]))]).err(), Some(ParentPointerError::Cycle { node: k[0] }));
+	}
+
+	#[test]
+	fn test_to_parent_pointers() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), 0);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+		tree.insert_child(d, a, usize::MAX);
+
+		let flat = tree.to_parent_pointers(root);
+		assert_eq!(flat, vec![(root, TreeKey::null(), 0), (a, root, 1), (c, a, 2), (d, a, 2), (b, root, 1)]);
+		assert_eq!(tree.to_parent_pointers(a), vec![(a, TreeKey::null(), 0), (c, a, 1), (d, a, 1)]);
+
+		// 往返
+		let pairs: Vec<(TreeKey, Option<TreeKey>)> = flat.iter().map(|(node, parent, _)| (*node, if parent.is_null() { None } else { Some(*parent) })).collect();
+		let tree1 = Tree::from_parent_pointers(&pairs).unwrap();
+		assert_eq!(tree1.to_parent_pointers(root), flat);
+		assert_eq!(tree1.validate_subtree(root), Ok(()));
+	}
+
+	#[test]
+	fn test_frontier() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		let e = slotmap.insert(());
+		let f = slotmap.insert(());
+		// root -> [a -> [c -> [e], d], b -> [f]], b的子树较浅
+		tree.insert_child(root, TreeKey::null(), 0);
+		tree.insert_child(a, root, usize::MAX);
+		tree.insert_child(b, root, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+		tree.insert_child(d, a, usize::MAX);
+		tree.insert_child(e, c, usize::MAX);
+		tree.insert_child(f, b, usize::MAX);
+		let g = slotmap.insert(());
+		tree.insert_child(g, root, usize::MAX);
+
+		assert_eq!(tree.frontier(root, 0).collect::<Vec<_>>(), vec![root]);
+		assert_eq!(tree.frontier(root, 1).collect::<Vec<_>>(), vec![a, b, g]);
+		assert_eq!(tree.frontier(root, 2).collect::<Vec<_>>(), vec![c, d, f, g]);
+		assert_eq!(tree.frontier(root, 3).collect::<Vec<_>>(), vec![e, d, f, g]);
+		// 深度超过树高，等于所有叶子节点
+		assert_eq!(tree.frontier(root, 10).collect::<Vec<_>>(), vec![e, d, f, g]);
+		assert_eq!(tree.frontier(a, 1).collect::<Vec<_>>(), vec![c, d]);
+		assert_eq!(tree.frontier(TreeKey::null(), 1).count(), 0);
+	}
+
+	#[cfg(feature = "std")]
+	#[test]
+	fn test_transfer_into() {
+		let mut tree: Tree<TreeKey, HashMapTree<TreeKey>> = Tree::new(HashMapTree::default());
+
+		let mut slotmap = SlotMap::default();
+		let k: Vec<TreeKey> = (0..8).map(|_| slotmap.insert(())).collect();
+		tree.insert_child(k[0], TreeKey::null(), 0);
+		tree.insert_child(k[1], TreeKey::null(), usize::MAX);
+		tree.insert_child(k[2], k[0], usize::MAX);
+		tree.insert_child(k[3], k[0], usize::MAX);
+		tree.insert_child(k[4], k[2], usize::MAX);
+		tree.insert_child(k[5], k[1], usize::MAX);
+		tree.insert_child(k[6], k[0], 0);
+		// 游离节点不会复制
+		tree.insert_child(k[7], k[3], 0);
+		tree.remove(k[7]);
+
+		let roots: Vec<TreeKey> = tree.roots().collect();
+		let expected: Vec<Vec<(TreeKey, TreeKey, usize)>> = roots.iter().map(|r| tree.to_parent_pointers(*r)).collect();
+
+		let tree1: Tree<TreeKey, SlotMapTree> = tree.transfer_into(SlotMapTree::default(), &roots);
+		assert_eq!(tree1.roots().collect::<Vec<_>>(), roots);
+		for (root, flat) in roots.iter().zip(expected.iter()) {
+			assert_eq!(tree1.to_parent_pointers(*root), *flat);
+			assert_eq!(tree1.validate_subtree(*root), Ok(()));
 		}
-		let r = self.head;
-        match self.inner.get_up(self.head) {
-			Some(up) => self.head = up.next,
-			None => self.head = K::null(),
-		};
-        Some(r)
-    }
-}
+		assert_eq!(tree1.len(), 7);
+		assert!(!tree1.exists(k[7]));
 
-pub struct RecursiveIterator<'a, K: Null, S: Storage<K>> {
-    inner: &'a S,
-    arr: [K; 32],
-    len: usize,
-}
+		// 再转换回来
+		let tree2: Tree<TreeKey, HashMapTree<TreeKey>> = tree1.transfer_into(HashMapTree::default(), &roots);
+		for (root, flat) in roots.iter().zip(expected.iter()) {
+			assert_eq!(tree2.to_parent_pointers(*root), *flat);
+			assert_eq!(tree2.validate_subtree(*root), Ok(()));
+		}
 
-impl<'a, K: Null + Copy + Clone, S: Storage<K>> RecursiveIterator<'a, K, S> {
-	pub fn new(s: &'a S, head: K, len: usize) -> Self {
-		RecursiveIterator {
-			inner: s,
-			arr: [
-				head,
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-				K::null(),
-			],
-			len,
+		// 不记录根节点的存储，由roots给出要复制的根节点；Tree的设置一起转移
+		let mut tree3: Tree<usize, VecTree> = Tree::new(VecTree::default());
+		tree3.set_checks(true);
+		tree3.insert_child(0, usize::null(), 0);
+		tree3.insert_child(1, 0, 0);
+		tree3.insert_child(2, 0, usize::MAX);
+		tree3.freeze_counts();
+		tree3.insert_child(3, 1, 0);
+		let tree4: Tree<usize, HashMapTree<usize>> = tree3.transfer_into(HashMapTree::default(), &[0]);
+		assert!(tree4.checks);
+		assert!(tree4.counts_frozen);
+		assert_eq!(tree4.collect_children(0), vec![1, 2]);
+		assert_eq!(tree4.descendant_count(0), 3);
+		assert_eq!(tree4.validate(), Ok(()));
+	}
+
+	#[test]
+	fn test_shrink_to_fit() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), 0);
+		let c: Vec<TreeKey> = (0..100).map(|_| slotmap.insert(())).collect();
+		for k in c.iter() {
+			tree.insert_child(*k, root, usize::MAX);
+		}
+		tree.insert_child(slotmap.insert(()), c[1], 0);
+		for k in c[10..].iter() {
+			tree.remove_recursive(*k);
 		}
+
+		let before = tree.to_parent_pointers(root);
+		tree.shrink_to_fit();
+		assert_eq!(tree.to_parent_pointers(root), before);
+		assert_eq!(tree.validate_subtree(root), Ok(()));
+		assert_eq!(tree.len(), 12);
+
+		// 收缩后仍然可以正常插入、删除
+		tree.insert_child(c[50], c[2], 0);
+		tree.remove(c[3]);
+		assert_eq!(tree.len(), 12);
+		assert_eq!(tree.validate_subtree(root), Ok(()));
 	}
-}
 
-impl<'a, K: Null + Copy + Clone, S: Storage<K>> Iterator for RecursiveIterator<'a, K, S> {
-    type Item = K;
+	#[test]
+	fn test_remove_children() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let p = slotmap.insert(());
+		let q = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), 0);
+		tree.insert_child(p, root, usize::MAX);
+		tree.insert_child(q, root, usize::MAX);
+		tree.insert_child(a, p, usize::MAX);
+		tree.insert_child(b, p, usize::MAX);
+		tree.insert_child(c, a, usize::MAX);
+		assert_eq!(tree.get_down(root).unwrap().count(), 5);
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.len == 0 {
-            return None;
-        }
-        self.len -= 1;
-        let head = self.arr[self.len];
-		if let Some(up) = self.inner.get_up(head) {
-			if !up.next.is_null() {
-				self.arr[self.len] = up.next;
-				self.len += 1;
-			}
-		}
+		tree.remove_children(p);
+		let down = tree.get_down(p).unwrap();
+		assert_eq!((down.len(), down.count(), down.head(), down.tail()), (0, 0, TreeKey::null(), TreeKey::null()));
+		assert_eq!(tree.get_down(root).unwrap().count(), 2);
+		assert_eq!(tree.validate_subtree(root), Ok(()));
 
-		if let Some(down) = self.inner.get_down(head) {
-			if !down.head.is_null(){
-				self.arr[self.len] = down.head;
-				self.len += 1;
-			}
-		};
+		// 子节点的状态与remove之后相同
+		assert!(tree.get_up(a).is_none());
+		assert!(!tree.is_attached(a) && !tree.is_attached(b) && !tree.is_attached(c));
+		assert_eq!(tree.get_up(c).unwrap().parent(), a);
+		tree.insert_child(a, q, 0);
+		assert_eq!(tree.get_down(root).unwrap().count(), 4);
+		assert_eq!(tree.depth(c), Some(4));
+		assert_eq!(tree.validate_subtree(root), Ok(()));
 
-        Some(head)
-    }
+		// 没有子节点时什么也不做
+		tree.remove_children(b);
+		tree.remove_children(p);
+		assert_eq!(tree.validate_subtree(root), Ok(()));
+	}
+
+	#[test]
+	fn test_find_ancestor() {
+		let (mut tree, mut slotmap) = fixture();
+		let root = slotmap.insert(());
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		tree.insert_child(root, TreeKey::null(), 0);
+		tree.insert_child(a, root, 0);
+		tree.insert_child(b, a, 0);
+		tree.insert_child(c, b, 0);
+
+		let containers = [root, a];
+		assert_eq!(tree.find_ancestor(c, |k| containers.contains(&k)), Some(a));
+		// 不包含节点自身
+		assert_eq!(tree.find_ancestor(a, |k| containers.contains(&k)), Some(root));
+		assert_eq!(tree.find_ancestor(c, |k| k == c), None);
+		assert_eq!(tree.find_ancestor(root, |_| true), None);
+
+		// 按从近到远的顺序调用pred
+		let mut visited = Vec::new();
+		assert_eq!(tree.find_ancestor(c, |k| { visited.push(k); false }), None);
+		assert_eq!(visited, vec![b, a, root]);
+	}
+
+	#[test]
+	fn test_roots_order() {
+		let (mut tree, mut slotmap) = fixture();
+		let a = slotmap.insert(());
+		let b = slotmap.insert(());
+		let c = slotmap.insert(());
+		let d = slotmap.insert(());
+		tree.insert_child(a, TreeKey::null(), 0);
+		tree.insert_child(b, TreeKey::null(), 0);
+		tree.insert_child(c, TreeKey::null(), 0);
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![a, b, c]);
+
+		tree.remove(b);
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![a, c]);
+
+		// 重复设置不改变位置，重新插入的根节点排在最后
+		tree.ensure_root(a);
+		tree.insert_child(d, TreeKey::null(), 0);
+		tree.insert_child(b, TreeKey::null(), 0);
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![a, c, d, b]);
+
+		// 根节点变为子节点，其余根节点顺序不变
+		tree.move_subtree(c, a, 0);
+		assert_eq!(tree.roots().collect::<Vec<_>>(), vec![a, d, b]);
+	}
 }
\ No newline at end of file
@@ -0,0 +1,110 @@
+//! 按成为根节点的先后顺序记录根节点的双向链表，SlotMapTree、VecTree、HashMapTree共用
+//! 每个根节点的前后根节点存放在以根节点为key的映射中（由各存储按自己的key类型提供），记录、删除根节点都是O(1)，删除不改变其余根节点的顺序
+
+use alloc::vec::Vec;
+use pi_null::Null;
+use serde::{Serialize, Deserialize};
+
+/// 根节点在链表中的前一个、后一个根节点，没有时为null
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RootLink<K> {
+	pub prev: K,
+	pub next: K,
+}
+
+/// 存放RootLink的映射
+pub(crate) trait RootMap<K> {
+	fn get(&self, k: K) -> Option<&RootLink<K>>;
+	fn get_mut(&mut self, k: K) -> Option<&mut RootLink<K>>;
+	fn insert(&mut self, k: K, link: RootLink<K>);
+	fn remove(&mut self, k: K) -> Option<RootLink<K>>;
+	fn clear(&mut self);
+}
+
+pub(crate) struct Roots<K, M> {
+	head: K,
+	tail: K,
+	pub map: M,
+}
+
+impl<K: Null, M: Default> Default for Roots<K, M> {
+	fn default() -> Self {
+		Roots { head: K::null(), tail: K::null(), map: M::default() }
+	}
+}
+
+impl<K: Null + Copy, M: RootMap<K>> Roots<K, M> {
+	/// 第一个根节点，没有根节点时为null
+	pub fn first(&self) -> K {
+		self.head
+	}
+
+	/// 根节点k的下一个根节点，k是最后一个根节点或不是根节点时为null
+	pub fn next(&self, k: K) -> K {
+		self.map.get(k).map_or(K::null(), |link|{link.next})
+	}
+
+	/// 追加到链表的最后，k已经是根节点时保持原来的位置
+	pub fn insert(&mut self, k: K) {
+		if self.map.get(k).is_some() {
+			return;
+		}
+		self.map.insert(k, RootLink { prev: self.tail, next: K::null() });
+		if self.tail.is_null() {
+			self.head = k;
+		} else {
+			self.map.get_mut(self.tail).unwrap().next = k;
+		}
+		self.tail = k;
+	}
+
+	pub fn remove(&mut self, k: K) {
+		if let Some(link) = self.map.remove(k) {
+			if link.prev.is_null() {
+				self.head = link.next;
+			} else {
+				self.map.get_mut(link.prev).unwrap().next = link.next;
+			}
+			if link.next.is_null() {
+				self.tail = link.prev;
+			} else {
+				self.map.get_mut(link.next).unwrap().prev = link.prev;
+			}
+		}
+	}
+
+	pub fn clear(&mut self) {
+		self.head = K::null();
+		self.tail = K::null();
+		self.map.clear();
+	}
+
+	fn iter(&self) -> impl Iterator<Item = K> + '_ {
+		let mut cur = self.head;
+		core::iter::from_fn(move || {
+			if cur.is_null() {
+				return None;
+			}
+			let r = cur;
+			cur = self.next(r);
+			Some(r)
+		})
+	}
+}
+
+// 序列化时只按顺序保存根节点列表，反序列化时按顺序重新插入，重建链表
+impl<K: Null + Copy + Serialize, M: RootMap<K>> Serialize for Roots<K, M> {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_seq(self.iter())
+	}
+}
+
+impl<'de, K: Null + Copy + Deserialize<'de>, M: RootMap<K> + Default> Deserialize<'de> for Roots<K, M> {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let mut roots = Roots::default();
+		for k in Vec::<K>::deserialize(deserializer)? {
+			roots.insert(k);
+		}
+		Ok(roots)
+	}
+}
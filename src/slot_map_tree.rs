@@ -1,10 +1,14 @@
 use pi_null::Null;
-use std::hash::Hash;
+use core::hash::Hash;
+use alloc::vec;
+use alloc::vec::Vec;
 use pi_slotmap::{DefaultKey as DefaultKey1, Key, KeyData, SecondaryMap, SlotMap};
+use serde::{Serialize, Deserialize};
 
-use crate::{Up, Down, Storage, StorageMut, Layer};
+use crate::{Up, Down, Storage, StorageMut, Layer, Tree, ParentPointerError};
+use crate::roots::{Roots, RootLink, RootMap};
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default, Serialize, Deserialize)]
 pub struct TreeKey(DefaultKey1);
 
 impl Key for TreeKey {
@@ -34,11 +38,117 @@ impl Null for TreeKey {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
 pub struct SlotMapTree {
 	up: SecondaryMap<TreeKey, Up<TreeKey>>,
 	down: SecondaryMap<TreeKey, Down<TreeKey>>,
 	layer: SecondaryMap<TreeKey, Layer<TreeKey>>,
+	roots: Roots<TreeKey, SecondaryMap<TreeKey, RootLink<TreeKey>>>,
+}
+
+impl RootMap<TreeKey> for SecondaryMap<TreeKey, RootLink<TreeKey>> {
+	fn get(&self, k: TreeKey) -> Option<&RootLink<TreeKey>> {
+		SecondaryMap::get(self, k)
+	}
+
+	fn get_mut(&mut self, k: TreeKey) -> Option<&mut RootLink<TreeKey>> {
+		SecondaryMap::get_mut(self, k)
+	}
+
+	fn insert(&mut self, k: TreeKey, link: RootLink<TreeKey>) {
+		SecondaryMap::insert(self, k, link);
+	}
+
+	fn remove(&mut self, k: TreeKey) -> Option<RootLink<TreeKey>> {
+		SecondaryMap::remove(self, k)
+	}
+
+	fn clear(&mut self) {
+		SecondaryMap::clear(self)
+	}
+}
+
+impl SlotMapTree {
+	/// 预分配n个节点的空间
+	pub fn with_capacity(n: usize) -> Self {
+		SlotMapTree {
+			up: SecondaryMap::with_capacity(n),
+			down: SecondaryMap::with_capacity(n),
+			layer: SecondaryMap::with_capacity(n),
+			roots: Roots::default(),
+		}
+	}
+
+	/// 释放已删除节点占用的多余容量
+	/// SecondaryMap按key的序号存储，不能原地收缩，这里重新收集仍然存在的节点，容量只保留到最大的序号
+	pub fn shrink_to_fit(&mut self) {
+		self.up = self.up.drain().collect();
+		self.down = self.down.drain().collect();
+		self.layer = self.layer.drain().collect();
+		self.roots.map = self.roots.map.drain().collect();
+	}
+}
+
+impl Tree<TreeKey, SlotMapTree> {
+	/// 预分配n个节点的空间
+	pub fn with_capacity(n: usize) -> Self {
+		Tree::new(SlotMapTree::with_capacity(n))
+	}
+
+	/// 从(节点, 父节点)数组构建树，父节点为None的节点作为根节点
+	/// 根节点、以及同一个父节点的子节点，都按在pairs中的顺序排列；pairs中父节点不需要出现在子节点之前
+	/// 节点重复、父节点不在pairs中、父节点关系形成环时返回错误
+	pub fn from_parent_pointers(pairs: &[(TreeKey, Option<TreeKey>)]) -> Result<Self, ParentPointerError<TreeKey>> {
+		let mut index: SecondaryMap<TreeKey, usize> = SecondaryMap::with_capacity(pairs.len());
+		for (i, (node, _)) in pairs.iter().enumerate() {
+			if index.insert(*node, i).is_some() {
+				return Err(ParentPointerError::DuplicateNode { node: *node });
+			}
+		}
+
+		// 按输入顺序收集根节点和每个节点的子节点
+		let mut roots = Vec::new();
+		let mut children: Vec<Vec<TreeKey>> = vec![Vec::new(); pairs.len()];
+		for (node, parent) in pairs.iter() {
+			match parent {
+				Some(parent) => match index.get(*parent) {
+					Some(i) => children[*i].push(*node),
+					None => return Err(ParentPointerError::DanglingParent { node: *node, parent: *parent }),
+				},
+				None => roots.push(*node),
+			}
+		}
+
+		// 从根节点先序遍历，无法到达的节点在环上（或挂在环上）
+		let mut preorder = Vec::with_capacity(pairs.len());
+		let mut arr: Vec<TreeKey> = roots.iter().rev().copied().collect();
+		while let Some(node) = arr.pop() {
+			preorder.push(node);
+			arr.extend(children[*index.get(node).unwrap()].iter().rev());
+		}
+		if preorder.len() != pairs.len() {
+			let mut visited: SecondaryMap<TreeKey, ()> = SecondaryMap::with_capacity(preorder.len());
+			for node in preorder.iter() {
+				visited.insert(*node, ());
+			}
+			let node = pairs.iter().map(|(node, _)|{*node}).find(|node|{!visited.contains_key(*node)}).unwrap();
+			return Err(ParentPointerError::Cycle { node });
+		}
+
+		// 逆先序链接子节点：链接时父节点还未挂到树上，不需要设置layer和向上修改count
+		// 最后插入根节点，一次设置整棵树的layer
+		let mut tree = Tree::with_capacity(pairs.len());
+		for node in preorder.iter().rev() {
+			let c = &children[*index.get(*node).unwrap()];
+			if !c.is_empty() {
+				tree.insert_children(*node, c);
+			}
+		}
+		for root in roots {
+			tree.insert_child(root, TreeKey::null(), usize::MAX);
+		}
+		Ok(tree)
+	}
 }
 
 impl Storage<TreeKey> for SlotMapTree {
@@ -62,6 +172,14 @@ impl Storage<TreeKey> for SlotMapTree {
         self.down.get(k)
     }
 
+    fn first_root(&self) -> TreeKey {
+        self.roots.first()
+    }
+
+    fn next_root(&self, k: TreeKey) -> TreeKey {
+        self.roots.next(k)
+    }
+
     fn down(&self, k: TreeKey) -> &Down<TreeKey> {
         self.down.get(k).unwrap()
     }
@@ -108,10 +226,23 @@ impl StorageMut<TreeKey> for SlotMapTree {
         self.down.remove(k);
     }
 
-    fn set_root(&mut self, _k: TreeKey) {
+    fn set_root(&mut self, k: TreeKey) {
+        self.roots.insert(k);
+    }
+
+    fn remove_root(&mut self, k: TreeKey) {
+        self.roots.remove(k);
+    }
+
+    fn clear(&mut self) {
+        self.up.clear();
+        self.down.clear();
+        self.layer.clear();
+        self.roots.clear();
     }
 
-    fn remove_root(&mut self, _k: TreeKey) {
+    fn shrink_to_fit(&mut self) {
+        SlotMapTree::shrink_to_fit(self)
     }
 }
 
@@ -0,0 +1,153 @@
+use alloc::vec::Vec;
+
+use crate::{Up, Down, Storage, StorageMut, Layer};
+use crate::roots::{Roots, RootLink, RootMap};
+
+/// 以Vec存储节点信息的树，适用于key为稠密的小整数的情况
+#[derive(Default)]
+pub struct VecTree {
+	up: Vec<Option<Up<usize>>>,
+	down: Vec<Option<Down<usize>>>,
+	layer: Vec<Option<Layer<usize>>>,
+	roots: Roots<usize, Vec<Option<RootLink<usize>>>>,
+}
+
+// 在k位置上设置值，长度不足时扩充
+fn insert<T>(vec: &mut Vec<Option<T>>, k: usize, value: T) {
+	if k >= vec.len() {
+		vec.resize_with(k + 1, || None);
+	}
+	vec[k] = Some(value);
+}
+
+fn remove<T>(vec: &mut [Option<T>], k: usize) {
+	if let Some(r) = vec.get_mut(k) {
+		*r = None;
+	}
+}
+
+impl RootMap<usize> for Vec<Option<RootLink<usize>>> {
+	fn get(&self, k: usize) -> Option<&RootLink<usize>> {
+		<[Option<RootLink<usize>>]>::get(self, k).and_then(|r| r.as_ref())
+	}
+
+	fn get_mut(&mut self, k: usize) -> Option<&mut RootLink<usize>> {
+		<[Option<RootLink<usize>>]>::get_mut(self, k).and_then(|r| r.as_mut())
+	}
+
+	fn insert(&mut self, k: usize, link: RootLink<usize>) {
+		insert(self, k, link);
+	}
+
+	fn remove(&mut self, k: usize) -> Option<RootLink<usize>> {
+		<[Option<RootLink<usize>>]>::get_mut(self, k).and_then(|r| r.take())
+	}
+
+	fn clear(&mut self) {
+		Vec::clear(self)
+	}
+}
+
+impl Storage<usize> for VecTree {
+    fn get_up(&self, k: usize) -> Option<&Up<usize>> {
+        self.up.get(k).and_then(|r| r.as_ref())
+    }
+
+    fn up(&self, k: usize) -> &Up<usize> {
+        self.get_up(k).unwrap()
+    }
+
+    fn get_layer(&self, k: usize) -> Option<&Layer<usize>> {
+        self.layer.get(k).and_then(|r| r.as_ref())
+    }
+
+    fn layer(&self, k: usize) -> &Layer<usize> {
+        self.get_layer(k).unwrap()
+    }
+
+    fn get_down(&self, k: usize) -> Option<&Down<usize>> {
+        self.down.get(k).and_then(|r| r.as_ref())
+    }
+
+    fn first_root(&self) -> usize {
+        self.roots.first()
+    }
+
+    fn next_root(&self, k: usize) -> usize {
+        self.roots.next(k)
+    }
+
+    fn down(&self, k: usize) -> &Down<usize> {
+        self.get_down(k).unwrap()
+    }
+}
+
+impl StorageMut<usize> for VecTree {
+    fn get_up_mut(&mut self, k: usize) -> Option<&mut Up<usize>> {
+        self.up.get_mut(k).and_then(|r| r.as_mut())
+    }
+
+    fn up_mut(&mut self, k: usize) -> &mut Up<usize> {
+        self.get_up_mut(k).unwrap()
+    }
+
+    fn get_down_mut(&mut self, k: usize) -> Option<&mut Down<usize>> {
+        self.down.get_mut(k).and_then(|r| r.as_mut())
+    }
+
+    fn down_mut(&mut self, k: usize) -> &mut Down<usize> {
+        self.get_down_mut(k).unwrap()
+    }
+
+    fn set_up(&mut self, k: usize, parent: Up<usize>) {
+        insert(&mut self.up, k, parent);
+    }
+
+    fn remove_up(&mut self, k: usize) {
+        remove(&mut self.up, k);
+    }
+
+    fn set_layer(&mut self, k: usize, layer: Layer<usize>) {
+        insert(&mut self.layer, k, layer);
+    }
+
+    fn remove_layer(&mut self, k: usize) {
+		remove(&mut self.layer, k);
+    }
+
+    fn set_down(&mut self, k: usize, children: Down<usize>) {
+        insert(&mut self.down, k, children);
+    }
+
+    fn remove_down(&mut self, k: usize) {
+        remove(&mut self.down, k);
+    }
+
+    fn set_root(&mut self, k: usize) {
+        self.roots.insert(k);
+    }
+
+    fn remove_root(&mut self, k: usize) {
+        self.roots.remove(k);
+    }
+
+    fn clear(&mut self) {
+        self.up.clear();
+        self.down.clear();
+        self.layer.clear();
+        self.roots.clear();
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.up.shrink_to_fit();
+        self.down.shrink_to_fit();
+        self.layer.shrink_to_fit();
+        self.roots.map.shrink_to_fit();
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test() {
+    crate::tests::check_storage(crate::Tree::new(VecTree::default()), [0, 1, 2, 3, 4, 5, 6, 7, 8]);
+}